@@ -0,0 +1,93 @@
+// - compares how fast the apple quality data is aggregated per group under
+//   each strategy, splitting the rows into chunks and merging their partial
+//   aggregates
+// - run with `cargo bench`, each strategy is timed over the bundled csv and
+//   over a copy enlarged by repeating its rows
+use apple_quality_analysis::{
+    read_apple_quality_csv, summarize_with, AppleQuality, Strategy, DEFAULT_QUANTILES,
+};
+use prettytable::{format, Cell, Row, Table};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+// how many times the bundled rows are repeated in the enlarged copy
+const ENLARGE_FACTOR: usize = 100;
+// timed runs per strategy, the median is reported
+const ITERATIONS: usize = 10;
+
+fn enlarge(apples: &[AppleQuality], factor: usize) -> Vec<AppleQuality> {
+    (0..factor)
+        .flat_map(|copy| {
+            apples.iter().map(move |apple| AppleQuality {
+                id: apple.id + (copy * apples.len()) as i32,
+                quality: apple.quality.clone(),
+                ..*apple
+            })
+        })
+        .collect()
+}
+
+fn median_duration(mut run: impl FnMut()) -> Duration {
+    // one untimed run to warm up the caches and the rayon pool
+    run();
+
+    let mut durations: Vec<Duration> = (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            run();
+            start.elapsed()
+        })
+        .collect();
+    durations.sort();
+    durations[ITERATIONS / 2]
+}
+
+fn main() {
+    let file_path = concat!(env!("CARGO_MANIFEST_DIR"), "/../data/apple_quality.csv");
+    let bundled = read_apple_quality_csv(file_path).unwrap();
+    let enlarged = enlarge(&bundled, ENLARGE_FACTOR);
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get());
+
+    let datasets = [
+        ("bundled", Arc::new(bundled)),
+        ("enlarged", Arc::new(enlarged)),
+    ];
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(Row::new(
+        ["Dataset", "Rows", "Strategy", "Median", "Rows/s", "Speedup"]
+            .iter()
+            .map(|title| Cell::new(title))
+            .collect(),
+    ));
+
+    for (name, apples) in &datasets {
+        let mut sequential = None;
+        for strategy in Strategy::ALL {
+            let duration = median_duration(|| {
+                summarize_with(strategy, apples, "Quality", &DEFAULT_QUANTILES, threads);
+            });
+            let baseline = *sequential.get_or_insert(duration);
+
+            table.add_row(Row::new(vec![
+                Cell::new(name),
+                Cell::new(&apples.len().to_string()),
+                Cell::new(strategy.name()),
+                Cell::new(&format!("{:.2?}", duration)),
+                Cell::new(&format!(
+                    "{:.0}",
+                    apples.len() as f64 / duration.as_secs_f64()
+                )),
+                Cell::new(&format!(
+                    "{:.2}x",
+                    baseline.as_secs_f64() / duration.as_secs_f64()
+                )),
+            ]));
+        }
+    }
+
+    println!("{} threads available", threads);
+    table.printstd();
+}
@@ -0,0 +1,424 @@
+use fnv::FnvHasher;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{BufWriter, Read};
+use std::path::Path;
+
+use crate::group::GroupSummary;
+use crate::sketch::QuantileSketch;
+use crate::statistics::AttributeSummary;
+
+// - a running summary of one attribute that can be updated one value at a time
+// - two aggregates can be merged, so workers can summarize different parts of
+//   the data and combine their partial results at the end
+// - the mean and the sum of squared differences from it (`m2`) are kept with
+//   Welford's update and merged with Chan's formula, which stay accurate where
+//   a plain sum of squares loses the variance to rounding, e.g. for values
+//   far from zero
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AttributeAggregate {
+    pub count: usize,
+    pub mean: f64,
+    pub m2: f64,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub sketch: QuantileSketch,
+}
+
+impl AttributeAggregate {
+    pub fn push(&mut self, value: f64) {
+        self.count += 1;
+        let delta = value - self.mean;
+        self.mean += delta / self.count as f64;
+        self.m2 += delta * (value - self.mean);
+        self.min = Some(self.min.map_or(value, |min| min.min(value)));
+        self.max = Some(self.max.map_or(value, |max| max.max(value)));
+        self.sketch.push(value);
+    }
+
+    pub fn merge(&mut self, other: &AttributeAggregate) {
+        if other.count > 0 {
+            let count = (self.count + other.count) as f64;
+            let delta = other.mean - self.mean;
+            self.mean += delta * other.count as f64 / count;
+            self.m2 += other.m2 + delta * delta * self.count as f64 * other.count as f64 / count;
+            self.count += other.count;
+        }
+        self.min = match (self.min, other.min) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        self.max = match (self.max, other.max) {
+            (Some(a), Some(b)) => Some(a.max(b)),
+            (a, b) => a.or(b),
+        };
+        self.sketch.merge(&other.sketch);
+    }
+
+    // - count, min and max are exact, the mean and the standard deviation equal
+    //   the in-memory ones up to rounding
+    // - the median and the quantiles are estimated from the sketch, so the
+    //   summary is flagged with `quantiles_estimated`
+    pub fn summary(&self, attribute: &str, quantiles: &[f64]) -> AttributeSummary {
+        let count = self.count as f64;
+        let mean = if self.count > 0 { self.mean } else { f64::NAN };
+        let std_dev = if self.count > 1 {
+            (self.m2 / (count - 1.0)).max(0.0).sqrt()
+        } else {
+            f64::NAN
+        };
+
+        AttributeSummary {
+            attribute: attribute.to_owned(),
+            count: self.count,
+            mean,
+            std_dev,
+            min: self.min.unwrap_or(f64::NAN),
+            max: self.max.unwrap_or(f64::NAN),
+            median: self.sketch.quantile(0.5),
+            quantiles: quantiles
+                .iter()
+                .map(|&q| (q, self.sketch.quantile(q)))
+                .collect(),
+            quantiles_estimated: true,
+        }
+    }
+}
+
+// one aggregate per numeric attribute, for each group label
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct GroupAggregates {
+    pub groups: HashMap<String, Vec<AttributeAggregate>>,
+}
+
+impl GroupAggregates {
+    // the values of one row, in the same order for every row
+    pub fn push(&mut self, label: &str, values: &[f64]) {
+        if !self.groups.contains_key(label) {
+            self.groups.insert(
+                label.to_owned(),
+                vec![AttributeAggregate::default(); values.len()],
+            );
+        }
+
+        let aggregates = self.groups.get_mut(label).unwrap();
+        for (aggregate, &value) in aggregates.iter_mut().zip(values) {
+            aggregate.push(value);
+        }
+    }
+
+    pub fn merge(&mut self, other: &GroupAggregates) {
+        for (label, other_aggregates) in &other.groups {
+            match self.groups.get_mut(label) {
+                Some(aggregates) => {
+                    for (aggregate, other_aggregate) in aggregates.iter_mut().zip(other_aggregates)
+                    {
+                        aggregate.merge(other_aggregate);
+                    }
+                }
+                None => {
+                    self.groups.insert(label.clone(), other_aggregates.clone());
+                }
+            }
+        }
+    }
+
+    // - turns the aggregates into summaries, in the order of the given labels
+    // - the attribute names are in the same order as the pushed values
+    pub fn summaries<S: AsRef<str>>(
+        &self,
+        attributes: &[S],
+        labels: &[String],
+        quantiles: &[f64],
+    ) -> Vec<GroupSummary> {
+        labels
+            .iter()
+            .filter_map(|label| {
+                self.groups.get(label).map(|aggregates| GroupSummary {
+                    group: label.clone(),
+                    attributes: attributes
+                        .iter()
+                        .zip(aggregates)
+                        .map(|(attribute, aggregate)| {
+                            aggregate.summary(attribute.as_ref(), quantiles)
+                        })
+                        .collect(),
+                })
+            })
+            .collect()
+    }
+}
+
+// - a file merged into a saved state, told apart from the others by a hash of
+//   its content, so the same batch is not counted twice even when it was
+//   copied or renamed
+// - the hash is FNV-1a, which stays the same across builds and platforms
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BatchId {
+    pub path: String,
+    pub content_hash: String,
+}
+
+impl BatchId {
+    pub fn of_file<P: AsRef<Path>>(file_path: P) -> Result<BatchId, Box<dyn Error>> {
+        let file_path = file_path.as_ref();
+        let mut hasher = FnvHasher::default();
+        let mut file = File::open(file_path)?;
+        let mut buffer = [0; 64 * 1024];
+        loop {
+            let read = file.read(&mut buffer)?;
+            if read == 0 {
+                break;
+            }
+            hasher.write(&buffer[..read]);
+        }
+
+        Ok(BatchId {
+            path: file_path.display().to_string(),
+            content_hash: format!("{:016x}", hasher.finish()),
+        })
+    }
+}
+
+// - the aggregates of every batch ingested so far, saved to a file between runs
+//   so a new batch can be merged in without reading the earlier ones again
+// - the summaries of the merged state are the ones a streaming run over all
+//   the batches at once would report, the median and the quantiles are
+//   estimates from the sketches
+// - the values are finite, NaN and infinity are rejected before they reach
+//   the aggregates, and serde_json's float_roundtrip feature reads every
+//   number back exactly
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct AggregateState {
+    pub group_column: String,
+    // the attribute names, in the order of the values in each aggregate
+    pub attributes: Vec<String>,
+    // the labels in the order they first appeared across all batches
+    pub labels: Vec<String>,
+    pub aggregates: GroupAggregates,
+    // the files merged into the state, in the order they were merged
+    #[serde(default)]
+    pub batches: Vec<BatchId>,
+    // the source of the --filter every batch was filtered with, batches
+    // filtered differently would mix rows that do not belong together
+    #[serde(default)]
+    pub filter: Option<String>,
+}
+
+impl AggregateState {
+    pub fn load<P: AsRef<Path>>(file_path: P) -> Result<AggregateState, Box<dyn Error>> {
+        let file = File::open(file_path)?;
+        Ok(serde_json::from_reader(file)?)
+    }
+
+    // - writes to a temporary file first and renames it over the old state, so
+    //   a failed write never leaves a half-written state behind
+    pub fn save<P: AsRef<Path>>(&self, file_path: P) -> Result<(), Box<dyn Error>> {
+        let file_path = file_path.as_ref();
+        let mut temporary = file_path.as_os_str().to_owned();
+        temporary.push(".tmp");
+
+        serde_json::to_writer(BufWriter::new(File::create(&temporary)?), self)?;
+        fs::rename(&temporary, file_path)?;
+        Ok(())
+    }
+
+    // the batch already merged into the state with the same content, if any
+    pub fn find_batch(&self, batch: &BatchId) -> Option<&BatchId> {
+        self.batches
+            .iter()
+            .find(|merged| merged.content_hash == batch.content_hash)
+    }
+
+    // - merges a later batch in, both must have the same group column,
+    //   attributes and filter
+    // - a batch that was merged before is rejected, it would be counted twice
+    pub fn merge(&mut self, other: &AggregateState) -> Result<(), Box<dyn Error>> {
+        for batch in &other.batches {
+            if let Some(merged) = self.find_batch(batch) {
+                return Err(format!(
+                    "{} was already merged into the saved state as {}",
+                    batch.path, merged.path
+                )
+                .into());
+            }
+        }
+        if !self.group_column.eq_ignore_ascii_case(&other.group_column) {
+            return Err(format!(
+                "the saved state is grouped by {:?}, not by {:?}",
+                self.group_column, other.group_column
+            )
+            .into());
+        }
+        if self.attributes != other.attributes {
+            return Err(format!(
+                "the saved state has the attributes {:?}, not {:?}",
+                self.attributes, other.attributes
+            )
+            .into());
+        }
+        if self.filter != other.filter {
+            return Err(format!(
+                "the saved state was built with {}, not with {}",
+                filter_description(&self.filter),
+                filter_description(&other.filter)
+            )
+            .into());
+        }
+
+        for label in &other.labels {
+            if !self.labels.contains(label) {
+                self.labels.push(label.clone());
+            }
+        }
+        self.aggregates.merge(&other.aggregates);
+        self.batches.extend_from_slice(&other.batches);
+        Ok(())
+    }
+
+    pub fn summaries(&self, quantiles: &[f64]) -> Vec<GroupSummary> {
+        self.aggregates
+            .summaries(&self.attributes, &self.labels, quantiles)
+    }
+}
+
+fn filter_description(filter: &Option<String>) -> String {
+    match filter {
+        Some(text) => format!("--filter {:?}", text),
+        None => "no filter".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aggregate_of(values: &[f64]) -> AttributeAggregate {
+        let mut aggregate = AttributeAggregate::default();
+        for &value in values {
+            aggregate.push(value);
+        }
+        aggregate
+    }
+
+    fn state_of(label: &str, rows: &[[f64; 2]], path: &str) -> AggregateState {
+        let mut aggregates = GroupAggregates::default();
+        for row in rows {
+            aggregates.push(label, row);
+        }
+        AggregateState {
+            group_column: "Quality".to_owned(),
+            attributes: vec!["Size".to_owned(), "Weight".to_owned()],
+            labels: vec![label.to_owned()],
+            aggregates,
+            batches: vec![BatchId {
+                path: path.to_owned(),
+                content_hash: format!("hash of {}", path),
+            }],
+            filter: None,
+        }
+    }
+
+    #[test]
+    fn the_variance_survives_values_far_from_zero() {
+        // a sum of squares of values around 1e9 loses their variance of 30
+        let aggregate = aggregate_of(&[1e9 + 4.0, 1e9 + 7.0, 1e9 + 13.0, 1e9 + 16.0]);
+        let summary = aggregate.summary("Size", &[]);
+
+        assert_eq!(summary.mean, 1e9 + 10.0);
+        assert!((summary.std_dev - 30f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn merged_parts_match_one_pass_over_all_values() {
+        let values: Vec<f64> = (0..1000).map(|i| (i as f64 * 0.37).sin() * 5.0).collect();
+        let whole = aggregate_of(&values);
+
+        let mut merged = aggregate_of(&values[..300]);
+        merged.merge(&aggregate_of(&values[300..]));
+        merged.merge(&AttributeAggregate::default());
+
+        assert_eq!(merged.count, whole.count);
+        assert_eq!((merged.min, merged.max), (whole.min, whole.max));
+        assert!((merged.mean - whole.mean).abs() < 1e-12);
+        assert!((merged.m2 - whole.m2).abs() < 1e-9 * whole.m2);
+    }
+
+    #[test]
+    fn a_saved_state_loads_back_unchanged() {
+        let rows: Vec<[f64; 2]> = (0..2000)
+            .map(|i| [(i as f64).sqrt() * 0.1, 1.0 / (i as f64 + 3.0)])
+            .collect();
+        let state = state_of("good", &rows, "day1.csv");
+
+        let file_path =
+            std::env::temp_dir().join(format!("apple_aggregate_state_{}.json", std::process::id()));
+        state.save(&file_path).unwrap();
+        let loaded = AggregateState::load(&file_path);
+        fs::remove_file(&file_path).unwrap();
+
+        assert_eq!(loaded.unwrap(), state);
+    }
+
+    #[test]
+    fn a_batch_is_merged_only_once() {
+        let mut state = state_of("good", &[[1.0, 2.0]], "day1.csv");
+        let day2 = state_of("bad", &[[3.0, 4.0]], "day2.csv");
+        state.merge(&day2).unwrap();
+        assert_eq!(state.labels, ["good", "bad"]);
+
+        // the same content under another name is still the same batch
+        let mut copy = day2.clone();
+        copy.batches[0].path = "copy of day2.csv".to_owned();
+        let error = state.merge(&copy).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "copy of day2.csv was already merged into the saved state as day2.csv"
+        );
+        assert_eq!(state.aggregates.groups["bad"][0].count, 1);
+    }
+
+    #[test]
+    fn batches_filtered_differently_are_not_merged() {
+        let mut state = state_of("good", &[[1.0, 2.0]], "day1.csv");
+        state.filter = Some("size > 0".to_owned());
+
+        let unfiltered = state_of("good", &[[-1.0, 2.0]], "day2.csv");
+        let error = state.merge(&unfiltered).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "the saved state was built with --filter \"size > 0\", not with no filter"
+        );
+        assert_eq!(state.aggregates.groups["good"][0].count, 1);
+        assert_eq!(state.batches.len(), 1);
+
+        let mut filtered = unfiltered;
+        filtered.filter = Some("size > 0".to_owned());
+        state.merge(&filtered).unwrap();
+        assert_eq!(state.aggregates.groups["good"][0].count, 2);
+    }
+
+    #[test]
+    fn the_batch_id_is_a_hash_of_the_content() {
+        let directory = std::env::temp_dir();
+        let id = std::process::id();
+        let (a, b) = (
+            directory.join(format!("apple_batch_a_{}.csv", id)),
+            directory.join(format!("apple_batch_b_{}.csv", id)),
+        );
+        fs::write(&a, "A_id,Size\n0,1.5\n").unwrap();
+        fs::write(&b, "A_id,Size\n0,1.5\n").unwrap();
+        let (id_a, id_b) = (BatchId::of_file(&a).unwrap(), BatchId::of_file(&b).unwrap());
+        fs::write(&b, "A_id,Size\n0,2.5\n").unwrap();
+        let changed = BatchId::of_file(&b).unwrap();
+        fs::remove_file(&a).unwrap();
+        fs::remove_file(&b).unwrap();
+
+        assert_eq!(id_a.content_hash, id_b.content_hash);
+        assert_ne!(id_a.path, id_b.path);
+        assert_ne!(changed.content_hash, id_b.content_hash);
+    }
+}
@@ -1,10 +1,12 @@
 use csv::ReaderBuilder;
-use prettytable::{format, Cell, Row, Table};
 use std::error::Error;
-use std::fs::File;
+use std::path::Path;
 
 use serde::Deserialize;
 
+use crate::statistics::AttributeSummary;
+use crate::validation::{non_finite_value, open_csv};
+
 #[derive(Debug, Deserialize)]
 pub struct AppleQuality {
     #[serde(rename = "A_id")]
@@ -27,35 +29,9 @@ pub struct AppleQuality {
     pub quality: String,
 }
 
-pub fn read_apple_quality_csv(file_path: &str) -> Result<Vec<AppleQuality>, Box<dyn Error>> {
-    let file = File::open(file_path)?;
-    let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(file);
-    let apple_quality: Vec<AppleQuality> = rdr.deserialize().collect::<Result<_, csv::Error>>()?;
-    Ok(apple_quality)
-}
-
-pub fn summarize_apples(apples: &Vec<&AppleQuality>) -> Vec<f64> {
-    let mut summary = vec![0.0; 7]; // We have 7 numeric fields to summarize
-    for apple in apples.iter() {
-        summary[0] += apple.size;
-        summary[1] += apple.weight;
-        summary[2] += apple.sweetness;
-        summary[3] += apple.crunchiness;
-        summary[4] += apple.juiciness;
-        summary[5] += apple.ripeness;
-        summary[6] += apple.acidity;
-    }
-
-    // Converting totals to averages
-    for val in &mut summary {
-        *val /= apples.len() as f64;
-    }
-
-    summary
-}
-
-pub fn display_comparative_summary_in_table(good_summary: &[f64], bad_summary: &[f64]) {
-    let attributes = [
+impl AppleQuality {
+    // the numeric columns that are summarized, in the order of the csv header
+    pub const ATTRIBUTES: [&'static str; 7] = [
         "Size",
         "Weight",
         "Sweetness",
@@ -65,21 +41,113 @@ pub fn display_comparative_summary_in_table(good_summary: &[f64], bad_summary: &
         "Acidity",
     ];
 
-    let mut table = Table::new();
-    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    // the numeric values of the apple, in the same order as ATTRIBUTES
+    pub fn attribute_values(&self) -> [f64; 7] {
+        [
+            self.size,
+            self.weight,
+            self.sweetness,
+            self.crunchiness,
+            self.juiciness,
+            self.ripeness,
+            self.acidity,
+        ]
+    }
+
+    // an apple with the given values, in the same order as ATTRIBUTES
+    #[cfg(test)]
+    pub(crate) fn from_values(id: i32, values: [f64; 7], quality: &str) -> AppleQuality {
+        let [size, weight, sweetness, crunchiness, juiciness, ripeness, acidity] = values;
+        AppleQuality {
+            id,
+            size,
+            weight,
+            sweetness,
+            crunchiness,
+            juiciness,
+            ripeness,
+            acidity,
+            quality: quality.to_owned(),
+        }
+    }
 
-    // Adding the header row with attributes and types of apple for comparison
-    table.add_row(row!["Attribute", "Good Apples", "Bad Apples"]);
+    // the text columns an apple can be grouped by
+    pub const GROUP_COLUMNS: [&'static str; 1] = ["Quality"];
 
-    // Iterating through attributes and appending respective summaries
-    for (i, &attr) in attributes.iter().enumerate() {
-        table.add_row(Row::new(vec![
-            Cell::new(attr),
-            Cell::new(&format!("{:.2}", good_summary[i])),
-            Cell::new(&format!("{:.2}", bad_summary[i])),
-        ]));
+    // returns the label of the apple for the given grouping column, the
+    // column name is matched case-insensitively against the csv header
+    pub fn group_label(&self, column: &str) -> Option<&str> {
+        if column.eq_ignore_ascii_case("Quality") {
+            Some(&self.quality)
+        } else {
+            None
+        }
     }
+}
 
-    // Printing the table
-    table.printstd();
+pub fn read_apple_quality_csv<P: AsRef<Path>>(
+    file_path: P,
+) -> Result<Vec<AppleQuality>, Box<dyn Error>> {
+    let file = open_csv(file_path)?;
+    let mut rdr = ReaderBuilder::new().has_headers(true).from_reader(file);
+    let headers = rdr.headers()?.clone();
+
+    let mut apple_quality = Vec::new();
+    for record in rdr.records() {
+        let record = record?;
+        let apple: AppleQuality = record.deserialize(Some(&headers))?;
+        // NaN and infinity are rejected like the streaming pipeline does, so
+        // both report the same summaries or the same error
+        if let Some(error) = non_finite_value(&apple, &record) {
+            return Err(error.to_string().into());
+        }
+        apple_quality.push(apple);
+    }
+    Ok(apple_quality)
+}
+
+pub fn summarize_apples(apples: &[&AppleQuality], quantiles: &[f64]) -> Vec<AttributeSummary> {
+    // one column of values for each of the 7 numeric fields
+    let mut columns = vec![Vec::with_capacity(apples.len()); AppleQuality::ATTRIBUTES.len()];
+    for apple in apples.iter() {
+        for (column, value) in columns.iter_mut().zip(apple.attribute_values()) {
+            column.push(value);
+        }
+    }
+
+    AppleQuality::ATTRIBUTES
+        .iter()
+        .zip(columns.iter_mut())
+        .map(|(attribute, values)| AttributeSummary::from_values(attribute, values, quantiles))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::pipeline::{summarize_csv_streaming, PipelineOptions};
+    use std::fs;
+
+    #[test]
+    fn strict_loading_rejects_non_finite_values_like_streaming() {
+        let file_path =
+            std::env::temp_dir().join(format!("apple_non_finite_{}.csv", std::process::id()));
+        fs::write(
+            &file_path,
+            "A_id,Size,Weight,Sweetness,Crunchiness,Juiciness,Ripeness,Acidity,Quality\n\
+             0,1,1,1,1,1,1,1,good\n\
+             1,1,1,NaN,1,1,1,1,bad\n",
+        )
+        .unwrap();
+
+        let in_memory = read_apple_quality_csv(&file_path).map(|apples| apples.len());
+        let streaming =
+            summarize_csv_streaming(&file_path, "Quality", &[], &PipelineOptions::default())
+                .map(|(summaries, _)| summaries.len());
+        fs::remove_file(&file_path).unwrap();
+
+        let expected = "line 3, Sweetness: \"NaN\" is not a finite number";
+        assert_eq!(in_memory.unwrap_err().to_string(), expected);
+        assert_eq!(streaming.unwrap_err().to_string(), expected);
+    }
 }
@@ -0,0 +1,126 @@
+use apple_quality_analysis::{
+    generate_apple_csv, read_apple_quality_csv, summarize_groups, ClassDistribution,
+    GeneratorOptions, DEFAULT_QUANTILES,
+};
+use clap::Parser;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::process::ExitCode;
+use std::sync::Arc;
+use std::time::Instant;
+
+#[derive(Debug, Parser)]
+#[command(about = "Generate a reproducible synthetic apple quality CSV file")]
+struct Args {
+    /// Path of the generated CSV file, written to stdout when not given
+    #[arg(short, long)]
+    output: Option<String>,
+
+    /// Number of apples to generate, at most 2147483647 as A_id is a 32-bit
+    /// number
+    #[arg(short = 'n', long, default_value_t = GeneratorOptions::default().rows)]
+    rows: u64,
+
+    /// Seed of the random number generator, the same seed gives the same file
+    #[arg(long, default_value_t = GeneratorOptions::default().seed)]
+    seed: u64,
+
+    /// Share of each quality label, e.g. "good=0.7,bad=0.3"
+    #[arg(long, value_delimiter = ',', value_parser = parse_balance)]
+    balance: Option<Vec<(String, f64)>>,
+
+    /// JSON file with the label, weight, means and std_devs of each class
+    #[arg(long, conflicts_with = "fit")]
+    distributions: Option<String>,
+
+    /// Fit the per-class distributions on an existing apple quality CSV file
+    /// instead of using the built-in ones
+    #[arg(long)]
+    fit: Option<String>,
+
+    /// Number of producer threads, the file does not depend on it
+    #[arg(short, long, default_value_t = GeneratorOptions::default().producers)]
+    producers: usize,
+
+    /// Number of rows generated from one random stream, unlike --producers
+    /// it changes the generated file
+    #[arg(long, default_value_t = GeneratorOptions::default().chunk_size)]
+    chunk_size: u64,
+}
+
+fn parse_balance(value: &str) -> Result<(String, f64), String> {
+    let (label, share) = value
+        .split_once('=')
+        .ok_or_else(|| format!("{:?} is not label=share", value))?;
+    let share: f64 = share
+        .parse()
+        .map_err(|_| format!("{:?} is not a number", share))?;
+    if share >= 0.0 {
+        Ok((label.to_owned(), share))
+    } else {
+        Err(format!("the share of {:?} is negative", label))
+    }
+}
+
+// errors are printed with Display, like the analysis binary does
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    let mut classes = match (&args.distributions, &args.fit) {
+        (Some(distributions), _) => serde_json::from_reader(File::open(distributions)?)?,
+        (None, Some(fit)) => {
+            let apples = Arc::new(read_apple_quality_csv(fit)?);
+            let summaries = summarize_groups(apples, "Quality", &DEFAULT_QUANTILES, 1, None);
+            ClassDistribution::from_summaries(&summaries)
+        }
+        (None, None) => ClassDistribution::apple_defaults(),
+    };
+
+    // --balance replaces the weights, a class it does not mention is not generated
+    if let Some(balance) = &args.balance {
+        for class in &mut classes {
+            class.weight = 0.0;
+        }
+        for (label, share) in balance {
+            let class = classes
+                .iter_mut()
+                .find(|class| class.label == *label)
+                .ok_or_else(|| format!("there is no distribution for the label {:?}", label))?;
+            class.weight = *share;
+        }
+    }
+
+    let options = GeneratorOptions {
+        rows: args.rows,
+        seed: args.seed,
+        classes,
+        producers: args.producers,
+        chunk_size: args.chunk_size,
+    };
+
+    let start = Instant::now();
+    let out: Box<dyn Write> = match &args.output {
+        Some(output) => Box::new(BufWriter::new(File::create(output)?)),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    };
+    let rows = generate_apple_csv(out, &options)?;
+
+    // stdout may hold the csv itself, so the timing goes to stderr
+    eprintln!(
+        "Generated {} apples with {} producers in {:.2?}",
+        rows,
+        options.producers,
+        start.elapsed()
+    );
+
+    Ok(())
+}
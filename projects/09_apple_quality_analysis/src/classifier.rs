@@ -0,0 +1,496 @@
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use std::error::Error;
+use std::thread;
+
+use crate::apple_quality::AppleQuality;
+use crate::report::Section;
+
+const FEATURES: usize = AppleQuality::ATTRIBUTES.len();
+
+#[derive(Clone, Debug)]
+pub struct ClassifierOptions {
+    // the label predicted as positive, e.g. "good"
+    pub positive_label: String,
+    // the label predicted as negative, rows with any other label are left out
+    pub negative_label: String,
+    // share of the rows held out for the final evaluation
+    pub test_fraction: f64,
+    // number of cross-validation folds over the training rows, at least 2
+    pub folds: usize,
+    // the folds and the final model are trained on at most this many threads
+    pub threads: usize,
+    pub epochs: usize,
+    pub learning_rate: f64,
+    pub seed: u64,
+}
+
+impl Default for ClassifierOptions {
+    fn default() -> Self {
+        ClassifierOptions {
+            positive_label: "good".to_owned(),
+            negative_label: "bad".to_owned(),
+            test_fraction: 0.2,
+            folds: 5,
+            threads: 1,
+            epochs: 500,
+            learning_rate: 0.1,
+            seed: 42,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ConfusionMatrix {
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub true_negatives: usize,
+    pub false_negatives: usize,
+}
+
+impl ConfusionMatrix {
+    pub fn record(&mut self, actual: bool, predicted: bool) {
+        match (actual, predicted) {
+            (true, true) => self.true_positives += 1,
+            (false, true) => self.false_positives += 1,
+            (false, false) => self.true_negatives += 1,
+            (true, false) => self.false_negatives += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.true_positives + self.false_positives + self.true_negatives + self.false_negatives
+    }
+
+    // - the metrics are None when they would divide by zero, e.g. the precision
+    //   of a model that never predicted the positive label
+    // - the report shows them as "n/a" instead of NaN
+    pub fn accuracy(&self) -> Option<f64> {
+        ratio(self.true_positives + self.true_negatives, self.total())
+    }
+
+    pub fn precision(&self) -> Option<f64> {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_positives,
+        )
+    }
+
+    pub fn recall(&self) -> Option<f64> {
+        ratio(
+            self.true_positives,
+            self.true_positives + self.false_negatives,
+        )
+    }
+}
+
+fn ratio(numerator: usize, denominator: usize) -> Option<f64> {
+    (denominator > 0).then(|| numerator as f64 / denominator as f64)
+}
+
+fn format_metric(metric: Option<f64>) -> String {
+    metric.map_or_else(|| "n/a".to_owned(), |value| format!("{:.3}", value))
+}
+
+// - scales each feature to a mean of 0 and a standard deviation of 1
+// - fitted on the training rows only, so nothing leaks from the test rows
+#[derive(Clone, Debug, PartialEq)]
+pub struct Standardizer {
+    means: [f64; FEATURES],
+    std_devs: [f64; FEATURES],
+}
+
+impl Standardizer {
+    pub fn fit(rows: &[[f64; FEATURES]]) -> Standardizer {
+        let n = rows.len() as f64;
+        let mut means = [0.0; FEATURES];
+        let mut std_devs = [0.0; FEATURES];
+
+        for row in rows {
+            for (mean, value) in means.iter_mut().zip(row) {
+                *mean += value / n;
+            }
+        }
+        for row in rows {
+            for ((std_dev, mean), value) in std_devs.iter_mut().zip(&means).zip(row) {
+                *std_dev += (value - mean).powi(2) / n;
+            }
+        }
+        for std_dev in &mut std_devs {
+            // a constant feature is left unscaled instead of divided by zero
+            *std_dev = if *std_dev > 0.0 { std_dev.sqrt() } else { 1.0 };
+        }
+
+        Standardizer { means, std_devs }
+    }
+
+    pub fn transform(&self, row: &[f64; FEATURES]) -> [f64; FEATURES] {
+        let mut scaled = [0.0; FEATURES];
+        for (i, value) in scaled.iter_mut().enumerate() {
+            *value = (row[i] - self.means[i]) / self.std_devs[i];
+        }
+        scaled
+    }
+}
+
+// logistic regression trained with batch gradient descent on standardized features
+#[derive(Clone, Debug, PartialEq)]
+pub struct LogisticRegression {
+    standardizer: Standardizer,
+    weights: [f64; FEATURES],
+    bias: f64,
+}
+
+impl LogisticRegression {
+    pub fn train(
+        rows: &[[f64; FEATURES]],
+        labels: &[bool],
+        epochs: usize,
+        learning_rate: f64,
+    ) -> LogisticRegression {
+        let standardizer = Standardizer::fit(rows);
+        let scaled: Vec<[f64; FEATURES]> =
+            rows.iter().map(|row| standardizer.transform(row)).collect();
+
+        let n = scaled.len() as f64;
+        let mut weights = [0.0; FEATURES];
+        let mut bias = 0.0;
+
+        for _ in 0..epochs {
+            let mut weight_gradients = [0.0; FEATURES];
+            let mut bias_gradient = 0.0;
+
+            for (row, &label) in scaled.iter().zip(labels) {
+                let error = sigmoid(dot(&weights, row) + bias) - if label { 1.0 } else { 0.0 };
+                for (gradient, value) in weight_gradients.iter_mut().zip(row) {
+                    *gradient += error * value;
+                }
+                bias_gradient += error;
+            }
+
+            for (weight, gradient) in weights.iter_mut().zip(weight_gradients) {
+                *weight -= learning_rate * gradient / n;
+            }
+            bias -= learning_rate * bias_gradient / n;
+        }
+
+        LogisticRegression {
+            standardizer,
+            weights,
+            bias,
+        }
+    }
+
+    // the probability that the row belongs to the positive label
+    pub fn predict_probability(&self, row: &[f64; FEATURES]) -> f64 {
+        sigmoid(dot(&self.weights, &self.standardizer.transform(row)) + self.bias)
+    }
+
+    pub fn predict(&self, row: &[f64; FEATURES]) -> bool {
+        self.predict_probability(row) >= 0.5
+    }
+
+    pub fn evaluate(&self, rows: &[[f64; FEATURES]], labels: &[bool]) -> ConfusionMatrix {
+        let mut matrix = ConfusionMatrix::default();
+        for (row, &label) in rows.iter().zip(labels) {
+            matrix.record(label, self.predict(row));
+        }
+        matrix
+    }
+}
+
+fn sigmoid(x: f64) -> f64 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+fn dot(a: &[f64; FEATURES], b: &[f64; FEATURES]) -> f64 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassifierReport {
+    pub train_rows: usize,
+    pub hold_out: ConfusionMatrix,
+    // one confusion matrix per cross-validation fold
+    pub folds: Vec<ConfusionMatrix>,
+}
+
+// - shuffles the rows with a seeded rng and holds out `test_fraction` of them
+// - the k cross-validation folds over the training rows and the final model
+//   are trained at the same time, dealt out round-robin to at most `threads`
+//   scoped threads
+// - fewer than 2 folds, or fewer training rows than folds, is an error, some
+//   model would be trained or validated on no rows at all
+pub fn train_and_evaluate(
+    apples: &[AppleQuality],
+    options: &ClassifierOptions,
+) -> Result<ClassifierReport, Box<dyn Error>> {
+    if options.folds < 2 {
+        return Err(format!(
+            "cross-validation needs at least 2 folds, not {}",
+            options.folds
+        )
+        .into());
+    }
+
+    let mut rows: Vec<([f64; FEATURES], bool)> = apples
+        .iter()
+        .filter(|apple| {
+            apple.quality == options.positive_label || apple.quality == options.negative_label
+        })
+        .map(|apple| {
+            (
+                apple.attribute_values(),
+                apple.quality == options.positive_label,
+            )
+        })
+        .collect();
+
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    rows.shuffle(&mut rng);
+
+    let test_rows = (rows.len() as f64 * options.test_fraction).round() as usize;
+    let (test, train) = rows.split_at(test_rows);
+    let (train_features, train_labels): (Vec<_>, Vec<_>) = train.iter().copied().unzip();
+    let (test_features, test_labels): (Vec<_>, Vec<_>) = test.iter().copied().unzip();
+
+    if train.len() < options.folds {
+        return Err(format!(
+            "{} training rows labelled {:?} or {:?} cannot be split into {} folds",
+            train.len(),
+            options.positive_label,
+            options.negative_label,
+            options.folds
+        )
+        .into());
+    }
+    let folds = options.folds;
+
+    // - job `fold` trains on every training row outside of that fold, the
+    //   last job trains the final model on all of them
+    // - every k-th training row is in the validation part of a fold
+    let run_job = |job: usize| {
+        if job == folds {
+            return LogisticRegression::train(
+                &train_features,
+                &train_labels,
+                options.epochs,
+                options.learning_rate,
+            )
+            .evaluate(&test_features, &test_labels);
+        }
+
+        let (mut fit_features, mut fit_labels) = (Vec::new(), Vec::new());
+        let (mut validate_features, mut validate_labels) = (Vec::new(), Vec::new());
+        for (i, (row, &label)) in train_features.iter().zip(&train_labels).enumerate() {
+            if i % folds == job {
+                validate_features.push(*row);
+                validate_labels.push(label);
+            } else {
+                fit_features.push(*row);
+                fit_labels.push(label);
+            }
+        }
+
+        LogisticRegression::train(
+            &fit_features,
+            &fit_labels,
+            options.epochs,
+            options.learning_rate,
+        )
+        .evaluate(&validate_features, &validate_labels)
+    };
+
+    let jobs = folds + 1;
+    let workers = options.threads.max(1).min(jobs);
+    let mut results: Vec<(usize, ConfusionMatrix)> = thread::scope(|s| {
+        let handles: Vec<_> = (0..workers)
+            .map(|worker| {
+                let run_job = &run_job;
+                s.spawn(move || {
+                    (worker..jobs)
+                        .step_by(workers)
+                        .map(|job| (job, run_job(job)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+
+    // back in job order, the final model's matrix is the last one
+    results.sort_by_key(|(job, _)| *job);
+    let (_, hold_out) = results.pop().unwrap();
+    Ok(ClassifierReport {
+        train_rows: train_features.len(),
+        folds: results.into_iter().map(|(_, matrix)| matrix).collect(),
+        hold_out,
+    })
+}
+
+impl ClassifierReport {
+    pub fn sections(&self, options: &ClassifierOptions) -> Vec<Section> {
+        let metric_row = |name: String, matrix: &ConfusionMatrix| {
+            vec![
+                name,
+                matrix.total().to_string(),
+                format_metric(matrix.accuracy()),
+                format_metric(matrix.precision()),
+                format_metric(matrix.recall()),
+            ]
+        };
+        let metric_header = ["Split", "Rows", "Accuracy", "Precision", "Recall"]
+            .map(str::to_owned)
+            .to_vec();
+
+        let mut fold_rows: Vec<Vec<String>> = self
+            .folds
+            .iter()
+            .enumerate()
+            .map(|(i, matrix)| metric_row(format!("fold {}", i + 1), matrix))
+            .collect();
+        // the mean over the folds the metric is defined for
+        let mean = |metric: fn(&ConfusionMatrix) -> Option<f64>| {
+            let values: Vec<f64> = self.folds.iter().filter_map(metric).collect();
+            (!values.is_empty()).then(|| values.iter().sum::<f64>() / values.len() as f64)
+        };
+        fold_rows.push(vec![
+            "mean".to_owned(),
+            String::new(),
+            format_metric(mean(ConfusionMatrix::accuracy)),
+            format_metric(mean(ConfusionMatrix::precision)),
+            format_metric(mean(ConfusionMatrix::recall)),
+        ]);
+
+        let positive = format!("actual {}", options.positive_label);
+        let negative = format!("actual {}", options.negative_label);
+        vec![
+            Section {
+                title: format!("{}-fold cross-validation", self.folds.len()),
+                header: metric_header.clone(),
+                rows: fold_rows,
+            },
+            Section {
+                title: format!("Hold-out evaluation ({} training rows)", self.train_rows),
+                header: metric_header,
+                rows: vec![metric_row("test".to_owned(), &self.hold_out)],
+            },
+            Section {
+                title: "Hold-out confusion matrix".to_owned(),
+                header: vec![
+                    String::new(),
+                    format!("predicted {}", options.positive_label),
+                    format!("predicted {}", options.negative_label),
+                ],
+                rows: vec![
+                    vec![
+                        positive,
+                        self.hold_out.true_positives.to_string(),
+                        self.hold_out.false_negatives.to_string(),
+                    ],
+                    vec![
+                        negative,
+                        self.hold_out.false_positives.to_string(),
+                        self.hold_out.true_negatives.to_string(),
+                    ],
+                ],
+            },
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apple(id: i32, sweetness: f64, quality: &str) -> AppleQuality {
+        AppleQuality {
+            id,
+            size: 0.0,
+            weight: 0.0,
+            sweetness,
+            crunchiness: 0.0,
+            juiciness: 0.0,
+            ripeness: 0.0,
+            acidity: 0.0,
+            quality: quality.to_owned(),
+        }
+    }
+
+    #[test]
+    fn metrics_that_would_divide_by_zero_are_not_available() {
+        let mut matrix = ConfusionMatrix::default();
+        assert_eq!(matrix.accuracy(), None);
+
+        // never predicted positive, so there is no precision
+        matrix.record(true, false);
+        matrix.record(false, false);
+        assert_eq!(matrix.accuracy(), Some(0.5));
+        assert_eq!(matrix.precision(), None);
+        assert_eq!(matrix.recall(), Some(0.0));
+        assert_eq!(format_metric(matrix.precision()), "n/a");
+    }
+
+    #[test]
+    fn the_report_does_not_depend_on_the_number_of_threads() {
+        // sweet apples are good, sour ones bad
+        let apples: Vec<AppleQuality> = (0..60)
+            .map(|i| {
+                let good = i % 2 == 0;
+                let sweetness = if good { 1.0 } else { -1.0 } + (i % 7) as f64 * 0.1;
+                apple(i, sweetness, if good { "good" } else { "bad" })
+            })
+            .collect();
+
+        let options = |threads| ClassifierOptions {
+            folds: 4,
+            threads,
+            epochs: 50,
+            ..ClassifierOptions::default()
+        };
+        let single = train_and_evaluate(&apples, &options(1)).unwrap();
+        assert_eq!(single.folds.len(), 4);
+        assert_eq!(single.hold_out.total(), 12);
+        assert_eq!(single.hold_out.accuracy(), Some(1.0));
+        assert_eq!(train_and_evaluate(&apples, &options(3)).unwrap(), single);
+    }
+
+    #[test]
+    fn too_few_rows_or_folds_are_rejected() {
+        let apples: Vec<AppleQuality> = (0..5)
+            .map(|i| apple(i, i as f64, if i % 2 == 0 { "good" } else { "bad" }))
+            .collect();
+        let options = |folds| ClassifierOptions {
+            folds,
+            test_fraction: 0.2,
+            epochs: 5,
+            ..ClassifierOptions::default()
+        };
+
+        // 1 of the 5 rows is held out, leaving 4 to train on
+        assert!(train_and_evaluate(&apples, &options(4)).is_ok());
+        assert_eq!(
+            train_and_evaluate(&apples, &options(5))
+                .unwrap_err()
+                .to_string(),
+            "4 training rows labelled \"good\" or \"bad\" cannot be split into 5 folds"
+        );
+        assert_eq!(
+            train_and_evaluate(&apples, &options(1))
+                .unwrap_err()
+                .to_string(),
+            "cross-validation needs at least 2 folds, not 1"
+        );
+        assert_eq!(
+            train_and_evaluate(&[], &options(2))
+                .unwrap_err()
+                .to_string(),
+            "0 training rows labelled \"good\" or \"bad\" cannot be split into 2 folds"
+        );
+    }
+}
@@ -0,0 +1,194 @@
+use std::thread;
+
+use crate::apple_quality::AppleQuality;
+use crate::report::{ReportRow, Section};
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CorrelationMatrix {
+    // the group the matrix was computed for, "all" when no split was made
+    pub group: String,
+    pub attributes: Vec<String>,
+    // values[i][j] is the correlation between attributes i and j
+    pub values: Vec<Vec<f64>>,
+}
+
+// - the pearson correlation coefficient of two columns of the same length
+// - it is NaN when either column is constant, the correlation is undefined
+//   without any variance
+pub fn pearson(x: &[f64], y: &[f64]) -> f64 {
+    let n = x.len() as f64;
+    let mean_x = x.iter().sum::<f64>() / n;
+    let mean_y = y.iter().sum::<f64>() / n;
+
+    let mut covariance = 0.0;
+    let mut variance_x = 0.0;
+    let mut variance_y = 0.0;
+    for (a, b) in x.iter().zip(y) {
+        covariance += (a - mean_x) * (b - mean_y);
+        variance_x += (a - mean_x).powi(2);
+        variance_y += (b - mean_y).powi(2);
+    }
+
+    covariance / (variance_x * variance_y).sqrt()
+}
+
+// - computes the correlation of every pair of attributes
+// - the 21 distinct pairs are dealt out to at most `threads` scoped threads,
+//   which borrow the columns directly instead of cloning them into each thread
+pub fn correlation_matrix(
+    group: &str,
+    apples: &[&AppleQuality],
+    threads: usize,
+) -> CorrelationMatrix {
+    let attribute_count = AppleQuality::ATTRIBUTES.len();
+
+    // one column of values for each attribute
+    let mut columns = vec![Vec::with_capacity(apples.len()); attribute_count];
+    for apple in apples {
+        for (column, value) in columns.iter_mut().zip(apple.attribute_values()) {
+            column.push(value);
+        }
+    }
+
+    let pairs: Vec<(usize, usize)> = (0..attribute_count)
+        .flat_map(|i| (i + 1..attribute_count).map(move |j| (i, j)))
+        .collect();
+    let workers = threads.max(1).min(pairs.len());
+
+    let mut values = vec![vec![1.0; attribute_count]; attribute_count];
+
+    // create a scoped thread environment, the threads can borrow `columns`
+    // because they are joined before the scope ends
+    let results = thread::scope(|s| {
+        let handles: Vec<_> = (0..workers)
+            .map(|worker| {
+                let columns = &columns;
+                let worker_pairs: Vec<(usize, usize)> = pairs
+                    .iter()
+                    .copied()
+                    .skip(worker)
+                    .step_by(workers)
+                    .collect();
+                s.spawn(move || {
+                    worker_pairs
+                        .into_iter()
+                        .map(|(i, j)| (i, j, pearson(&columns[i], &columns[j])))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect::<Vec<_>>()
+    });
+
+    // the matrix is symmetric, so each result fills two cells
+    for (i, j, r) in results {
+        values[i][j] = r;
+        values[j][i] = r;
+    }
+
+    CorrelationMatrix {
+        group: group.to_owned(),
+        attributes: AppleQuality::ATTRIBUTES
+            .iter()
+            .map(|a| a.to_string())
+            .collect(),
+        values,
+    }
+}
+
+impl CorrelationMatrix {
+    pub fn section(&self) -> Section {
+        let mut header = vec!["Attribute".to_owned()];
+        header.extend(self.attributes.iter().cloned());
+
+        Section {
+            title: format!("Correlation matrix ({})", self.group),
+            header,
+            rows: self
+                .attributes
+                .iter()
+                .zip(&self.values)
+                .map(|(attribute, row)| {
+                    let mut cells = vec![attribute.clone()];
+                    cells.extend(row.iter().map(|r| format!("{:.2}", r)));
+                    cells
+                })
+                .collect(),
+        }
+    }
+
+    // one row per pair of attributes, e.g. (Size, good, correlation:Weight, -0.17)
+    pub fn report_rows(&self) -> Vec<ReportRow> {
+        let mut rows = Vec::new();
+        for (attribute, row) in self.attributes.iter().zip(&self.values) {
+            for (other, &value) in self.attributes.iter().zip(row) {
+                rows.push(ReportRow {
+                    attribute: attribute.clone(),
+                    group: self.group.clone(),
+                    statistic: format!("correlation:{}", other),
+                    value,
+                });
+            }
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_linear_relation_correlates_perfectly() {
+        let x = [1.0, 2.0, 3.0, 4.0, 5.0];
+        let rising: Vec<f64> = x.iter().map(|x| 2.0 * x + 1.0).collect();
+        let falling: Vec<f64> = x.iter().map(|x| 10.0 - 0.5 * x).collect();
+
+        assert!((pearson(&x, &rising) - 1.0).abs() < 1e-12);
+        assert!((pearson(&x, &falling) + 1.0).abs() < 1e-12);
+        assert!((pearson(&x, &[2.0, 1.0, 4.0, 3.0, 5.0]) - 0.8).abs() < 1e-12);
+    }
+
+    #[test]
+    fn a_constant_column_has_no_correlation() {
+        assert!(pearson(&[1.0, 2.0, 3.0], &[4.0, 4.0, 4.0]).is_nan());
+        assert!(pearson(&[], &[]).is_nan());
+    }
+
+    #[test]
+    fn the_matrix_is_symmetric_with_ones_on_the_diagonal() {
+        let apples: Vec<AppleQuality> = (0..50)
+            .map(|i| {
+                let x = i as f64;
+                let values = [
+                    x,
+                    -x,
+                    (x * 0.7).sin(),
+                    x * x,
+                    (x * 1.3).cos(),
+                    5.0 - x,
+                    x % 7.0,
+                ];
+                AppleQuality::from_values(i, values, "good")
+            })
+            .collect();
+        let apples: Vec<&AppleQuality> = apples.iter().collect();
+
+        let matrix = correlation_matrix("good", &apples, 4);
+        assert_eq!(matrix, correlation_matrix("good", &apples, 1));
+        assert_eq!(matrix.values.len(), AppleQuality::ATTRIBUTES.len());
+        for (i, row) in matrix.values.iter().enumerate() {
+            assert_eq!(row[i], 1.0);
+            for (j, &value) in row.iter().enumerate() {
+                assert_eq!(value, matrix.values[j][i]);
+                assert!((-1.0..=1.0).contains(&value), "{} {}", i, j);
+            }
+        }
+        assert!((matrix.values[0][1] + 1.0).abs() < 1e-12);
+        assert!((matrix.values[0][5] + 1.0).abs() < 1e-12);
+    }
+}
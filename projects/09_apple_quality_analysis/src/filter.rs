@@ -0,0 +1,502 @@
+use std::fmt;
+
+use crate::apple_quality::AppleQuality;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    Equal,
+    NotEqual,
+}
+
+impl Comparison {
+    fn holds<T: PartialOrd>(self, left: T, right: T) -> bool {
+        match self {
+            Comparison::Less => left < right,
+            Comparison::LessOrEqual => left <= right,
+            Comparison::Greater => left > right,
+            Comparison::GreaterOrEqual => left >= right,
+            Comparison::Equal => left == right,
+            Comparison::NotEqual => left != right,
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let symbol = match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Equal => "==",
+            Comparison::NotEqual => "!=",
+        };
+        write!(f, "{}", symbol)
+    }
+}
+
+// - a parsed filter expression such as `ripeness > 1.0 and acidity < 0`
+// - the column names are resolved when the filter is parsed, so testing a row
+//   only compares numbers and labels
+// - comparisons combine with `and`, `or`, `not` and parentheses, `and` binds
+//   tighter than `or`
+// - a column name with spaces or a leading digit is quoted, e.g.
+//   `"fruit size" > 1`
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    And(Box<Filter>, Box<Filter>),
+    Or(Box<Filter>, Box<Filter>),
+    Not(Box<Filter>),
+    // compares the value of the attribute at this position
+    Attribute {
+        attribute: usize,
+        comparison: Comparison,
+        value: f64,
+    },
+    // compares the group label, only == and != are allowed
+    Label {
+        comparison: Comparison,
+        value: String,
+    },
+}
+
+impl Filter {
+    // - `attributes` are the numeric columns in the order of the row values
+    // - `label_column` is the name of the column holding the group label
+    // - names are matched case-insensitively
+    pub fn parse<S: AsRef<str>>(
+        text: &str,
+        attributes: &[S],
+        label_column: &str,
+    ) -> Result<Filter, String> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            next: 0,
+            attributes: attributes.iter().map(|a| a.as_ref().to_owned()).collect(),
+            label_column: label_column.to_owned(),
+        };
+
+        let filter = parser.or()?;
+        match parser.peek() {
+            None => Ok(filter),
+            Some((position, token)) => Err(format!(
+                "unexpected {} at position {} of the filter",
+                token, position
+            )),
+        }
+    }
+
+    // a filter over the attributes and the quality label of AppleQuality
+    pub fn parse_apple(text: &str) -> Result<Filter, String> {
+        Filter::parse(
+            text,
+            &AppleQuality::ATTRIBUTES,
+            AppleQuality::GROUP_COLUMNS[0],
+        )
+    }
+
+    pub fn matches(&self, values: &[f64], label: &str) -> bool {
+        match self {
+            Filter::And(left, right) => left.matches(values, label) && right.matches(values, label),
+            Filter::Or(left, right) => left.matches(values, label) || right.matches(values, label),
+            Filter::Not(filter) => !filter.matches(values, label),
+            Filter::Attribute {
+                attribute,
+                comparison,
+                value,
+            } => comparison.holds(values[*attribute], *value),
+            Filter::Label { comparison, value } => comparison.holds(label, value.as_str()),
+        }
+    }
+
+    pub fn matches_apple(&self, apple: &AppleQuality) -> bool {
+        self.matches(&apple.attribute_values(), &apple.quality)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Name(String),
+    Number(f64),
+    Text(String),
+    Compare(Comparison),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Name(name) => write!(f, "{:?}", name),
+            Token::Number(number) => write!(f, "{}", number),
+            Token::Text(text) => write!(f, "{:?}", text),
+            Token::Compare(comparison) => write!(f, "\"{}\"", comparison),
+            Token::And => write!(f, "\"and\""),
+            Token::Or => write!(f, "\"or\""),
+            Token::Not => write!(f, "\"not\""),
+            Token::Open => write!(f, "\"(\""),
+            Token::Close => write!(f, "\")\""),
+        }
+    }
+}
+
+// splits the filter into tokens, each with its character position
+fn tokenize(text: &str) -> Result<Vec<(usize, Token)>, String> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+        let next = chars.get(i + 1).copied();
+
+        let token = match c {
+            _ if c.is_whitespace() => {
+                i += 1;
+                continue;
+            }
+            '(' => Token::Open,
+            ')' => Token::Close,
+            '<' | '>' | '=' | '!' if next == Some('=') => {
+                i += 1;
+                Token::Compare(match c {
+                    '<' => Comparison::LessOrEqual,
+                    '>' => Comparison::GreaterOrEqual,
+                    '=' => Comparison::Equal,
+                    _ => Comparison::NotEqual,
+                })
+            }
+            '<' => Token::Compare(Comparison::Less),
+            '>' => Token::Compare(Comparison::Greater),
+            '=' => Token::Compare(Comparison::Equal),
+            '!' => Token::Not,
+            '&' | '|' if next == Some(c) => {
+                i += 1;
+                if c == '&' {
+                    Token::And
+                } else {
+                    Token::Or
+                }
+            }
+            '"' | '\'' => {
+                let end = chars[i + 1..]
+                    .iter()
+                    .position(|&quote| quote == c)
+                    .ok_or_else(|| format!("unclosed quote at position {} of the filter", i))?;
+                let quoted: String = chars[i + 1..i + 1 + end].iter().collect();
+                i += end + 1;
+                Token::Text(quoted)
+            }
+            _ if c.is_ascii_digit() || c == '.' || c == '-' || c == '+' => {
+                // - a number is a sign, digits and points, and an exponent
+                // - it ends at the first other character, so `1and` is the
+                //   number 1 followed by "and"
+                let is_digit = |at: usize| chars.get(at).is_some_and(char::is_ascii_digit);
+                while is_digit(i + 1) || chars.get(i + 1) == Some(&'.') {
+                    i += 1;
+                }
+                if matches!(chars.get(i + 1), Some('e' | 'E')) {
+                    let sign = usize::from(matches!(chars.get(i + 2), Some('-' | '+')));
+                    if is_digit(i + 2 + sign) {
+                        i += 2 + sign;
+                        while is_digit(i + 1) {
+                            i += 1;
+                        }
+                    }
+                }
+                let number: String = chars[start..=i].iter().collect();
+                Token::Number(number.parse().map_err(|_| {
+                    format!(
+                        "{:?} at position {} of the filter is not a number",
+                        number, start
+                    )
+                })?)
+            }
+            _ if c.is_alphanumeric() || c == '_' => {
+                while i + 1 < chars.len() && (chars[i + 1].is_alphanumeric() || chars[i + 1] == '_')
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..=i].iter().collect();
+                match word.to_ascii_lowercase().as_str() {
+                    "and" => Token::And,
+                    "or" => Token::Or,
+                    "not" => Token::Not,
+                    _ => Token::Name(word),
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "unexpected {:?} at position {} of the filter",
+                    c, start
+                ))
+            }
+        };
+
+        tokens.push((start, token));
+        i += 1;
+    }
+
+    Ok(tokens)
+}
+
+// - a recursive descent parser, one method per precedence level
+// - or := and ("or" and)*
+// - and := not ("and" not)*
+// - not := "not" not | "(" or ")" | (name | quoted name) comparison value
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    next: usize,
+    attributes: Vec<String>,
+    label_column: String,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&(usize, Token)> {
+        self.tokens.get(self.next)
+    }
+
+    fn advance(&mut self, expected: &str) -> Result<(usize, Token), String> {
+        let token = self
+            .tokens
+            .get(self.next)
+            .cloned()
+            .ok_or_else(|| format!("the filter ended where {} was expected", expected))?;
+        self.next += 1;
+        Ok(token)
+    }
+
+    fn or(&mut self) -> Result<Filter, String> {
+        let mut filter = self.and()?;
+        while let Some((_, Token::Or)) = self.peek() {
+            self.next += 1;
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut filter = self.not()?;
+        while let Some((_, Token::And)) = self.peek() {
+            self.next += 1;
+            filter = Filter::And(Box::new(filter), Box::new(self.not()?));
+        }
+        Ok(filter)
+    }
+
+    fn not(&mut self) -> Result<Filter, String> {
+        match self.advance("a comparison")? {
+            (_, Token::Not) => Ok(Filter::Not(Box::new(self.not()?))),
+            (_, Token::Open) => {
+                let filter = self.or()?;
+                match self.advance("\")\"")? {
+                    (_, Token::Close) => Ok(filter),
+                    (position, token) => Err(format!(
+                        "expected \")\" but found {} at position {} of the filter",
+                        token, position
+                    )),
+                }
+            }
+            // a quoted name can hold spaces or start with a digit
+            (position, Token::Name(name) | Token::Text(name)) => self.comparison(position, &name),
+            (position, token) => Err(format!(
+                "expected a column name but found {} at position {} of the filter",
+                token, position
+            )),
+        }
+    }
+
+    fn comparison(&mut self, position: usize, name: &str) -> Result<Filter, String> {
+        let comparison = match self.advance("a comparison operator")? {
+            (_, Token::Compare(comparison)) => comparison,
+            (position, token) => {
+                return Err(format!(
+                    "expected a comparison operator but found {} at position {} of the filter",
+                    token, position
+                ))
+            }
+        };
+        let (value_position, value) = self.advance("a value")?;
+
+        if name.eq_ignore_ascii_case(&self.label_column) {
+            let value = match value {
+                Token::Text(text) | Token::Name(text) => text,
+                Token::Number(number) => number.to_string(),
+                token => {
+                    return Err(format!(
+                        "expected a label but found {} at position {} of the filter",
+                        token, value_position
+                    ))
+                }
+            };
+            if !matches!(comparison, Comparison::Equal | Comparison::NotEqual) {
+                return Err(format!(
+                    "{} can only be compared with == or !=, not {}",
+                    self.label_column, comparison
+                ));
+            }
+            return Ok(Filter::Label { comparison, value });
+        }
+
+        let attribute = self
+            .attributes
+            .iter()
+            .position(|attribute| attribute.eq_ignore_ascii_case(name))
+            .ok_or_else(|| {
+                format!(
+                    "unknown column {:?} at position {} of the filter, expected one of {:?} or {:?}",
+                    name, position, self.attributes, self.label_column
+                )
+            })?;
+        match value {
+            Token::Number(value) => Ok(Filter::Attribute {
+                attribute,
+                comparison,
+                value,
+            }),
+            token => Err(format!(
+                "expected a number but found {} at position {} of the filter",
+                token, value_position
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> Result<Filter, String> {
+        Filter::parse_apple(text)
+    }
+
+    fn attribute(attribute: usize, comparison: Comparison, value: f64) -> Filter {
+        Filter::Attribute {
+            attribute,
+            comparison,
+            value,
+        }
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let size = attribute(0, Comparison::Greater, 1.0);
+        let weight = attribute(1, Comparison::Less, 0.0);
+        let acidity = attribute(6, Comparison::Equal, 2.0);
+
+        assert_eq!(
+            parse("size > 1 or weight < 0 and acidity == 2").unwrap(),
+            Filter::Or(
+                Box::new(size.clone()),
+                Box::new(Filter::And(
+                    Box::new(weight.clone()),
+                    Box::new(acidity.clone())
+                ))
+            )
+        );
+        assert_eq!(
+            parse("(size > 1 || weight < 0) && acidity == 2").unwrap(),
+            Filter::And(
+                Box::new(Filter::Or(Box::new(size), Box::new(weight))),
+                Box::new(acidity)
+            )
+        );
+    }
+
+    #[test]
+    fn not_applies_to_the_next_comparison_only() {
+        let filter = parse("not quality == good and ripeness >= -1.5e-1").unwrap();
+        assert_eq!(
+            filter,
+            Filter::And(
+                Box::new(Filter::Not(Box::new(Filter::Label {
+                    comparison: Comparison::Equal,
+                    value: "good".to_owned(),
+                }))),
+                Box::new(attribute(5, Comparison::GreaterOrEqual, -0.15))
+            )
+        );
+        assert!(filter.matches(&[0.0; 7], "bad"));
+        assert!(!filter.matches(&[0.0, 0.0, 0.0, 0.0, 0.0, -1.0, 0.0], "bad"));
+    }
+
+    #[test]
+    fn a_number_ends_where_a_word_starts() {
+        assert_eq!(
+            parse("size>1and acidity<0").unwrap(),
+            Filter::And(
+                Box::new(attribute(0, Comparison::Greater, 1.0)),
+                Box::new(attribute(6, Comparison::Less, 0.0))
+            )
+        );
+        assert_eq!(
+            parse("size>2e3or size<.5").unwrap(),
+            Filter::Or(
+                Box::new(attribute(0, Comparison::Greater, 2000.0)),
+                Box::new(attribute(0, Comparison::Less, 0.5))
+            )
+        );
+    }
+
+    #[test]
+    fn quoted_column_names_can_hold_spaces_and_start_with_digits() {
+        let filter = Filter::parse(
+            "\"fruit size\" > 1 and '2nd weight' <= 0 and 'Harvest Site' != \"north field\"",
+            &["Fruit Size", "2nd Weight"],
+            "Harvest Site",
+        )
+        .unwrap();
+        assert_eq!(
+            filter,
+            Filter::And(
+                Box::new(Filter::And(
+                    Box::new(attribute(0, Comparison::Greater, 1.0)),
+                    Box::new(attribute(1, Comparison::LessOrEqual, 0.0))
+                )),
+                Box::new(Filter::Label {
+                    comparison: Comparison::NotEqual,
+                    value: "north field".to_owned(),
+                })
+            )
+        );
+        assert!(filter.matches(&[2.0, -1.0], "south field"));
+    }
+
+    #[test]
+    fn errors_point_at_the_offending_token() {
+        assert_eq!(
+            parse("size > 1 acidity < 0").unwrap_err(),
+            "unexpected \"acidity\" at position 9 of the filter"
+        );
+        assert_eq!(
+            parse("(size > 1").unwrap_err(),
+            "the filter ended where \")\" was expected"
+        );
+        assert_eq!(
+            parse("size > 1.2.3").unwrap_err(),
+            "\"1.2.3\" at position 7 of the filter is not a number"
+        );
+        assert_eq!(
+            parse("colour == red").unwrap_err(),
+            "unknown column \"colour\" at position 0 of the filter, expected one of \
+             [\"Size\", \"Weight\", \"Sweetness\", \"Crunchiness\", \"Juiciness\", \"Ripeness\", \
+             \"Acidity\"] or \"Quality\""
+        );
+        assert_eq!(
+            parse("quality > good").unwrap_err(),
+            "Quality can only be compared with == or !=, not >"
+        );
+        assert_eq!(
+            parse("size > 'big").unwrap_err(),
+            "unclosed quote at position 7 of the filter"
+        );
+    }
+}
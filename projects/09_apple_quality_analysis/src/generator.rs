@@ -0,0 +1,254 @@
+use crossbeam::channel;
+use csv::WriterBuilder;
+use rand::distributions::WeightedIndex;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use rand_distr::{Distribution, Normal};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::Write;
+use std::thread;
+
+use crate::apple_quality::AppleQuality;
+use crate::group::GroupSummary;
+
+const ATTRIBUTE_COUNT: usize = AppleQuality::ATTRIBUTES.len();
+
+// - the normal distribution of each attribute for one quality label
+// - `weight` is the share of the rows with this label, relative to the other classes
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClassDistribution {
+    pub label: String,
+    pub weight: f64,
+    // in the order of AppleQuality::ATTRIBUTES
+    pub means: [f64; ATTRIBUTE_COUNT],
+    pub std_devs: [f64; ATTRIBUTE_COUNT],
+}
+
+impl ClassDistribution {
+    // - one class per group of the summaries, weighted by its number of rows
+    // - used to generate data that looks like an existing file
+    pub fn from_summaries(summaries: &[GroupSummary]) -> Vec<ClassDistribution> {
+        summaries
+            .iter()
+            .map(|summary| {
+                let mut means = [0.0; ATTRIBUTE_COUNT];
+                let mut std_devs = [0.0; ATTRIBUTE_COUNT];
+                for (i, attribute) in summary.attributes.iter().enumerate() {
+                    means[i] = attribute.mean;
+                    std_devs[i] = attribute.std_dev;
+                }
+                ClassDistribution {
+                    label: summary.group.clone(),
+                    weight: summary.attributes.first().map_or(0.0, |a| a.count as f64),
+                    means,
+                    std_devs,
+                }
+            })
+            .collect()
+    }
+
+    // the good and bad classes fitted on the bundled apple_quality.csv
+    pub fn apple_defaults() -> Vec<ClassDistribution> {
+        vec![
+            ClassDistribution {
+                label: "good".to_owned(),
+                weight: 0.5,
+                means: [-0.0336, -0.9873, 0.0163, 0.9682, 1.0134, 0.0039, 0.0607],
+                std_devs: [1.9722, 1.8521, 1.9102, 1.5992, 1.6895, 1.7297, 2.0067],
+            },
+            ClassDistribution {
+                label: "bad".to_owned(),
+                weight: 0.5,
+                means: [-0.9744, -0.9918, -0.9592, 1.0029, 0.0089, 0.9946, 0.0932],
+                std_devs: [1.7615, 1.3052, 1.8522, 1.1731, 2.0241, 1.8833, 2.2097],
+            },
+        ]
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GeneratorOptions {
+    pub rows: u64,
+    pub seed: u64,
+    pub classes: Vec<ClassDistribution>,
+    // number of threads generating chunks of rows
+    pub producers: usize,
+    // number of rows generated together from one random stream
+    pub chunk_size: u64,
+}
+
+impl Default for GeneratorOptions {
+    fn default() -> Self {
+        GeneratorOptions {
+            rows: 4000,
+            seed: 42,
+            classes: ClassDistribution::apple_defaults(),
+            producers: thread::available_parallelism().map_or(1, |producers| producers.get()),
+            chunk_size: 10_000,
+        }
+    }
+}
+
+// - writes `rows` apples in the AppleQuality csv layout and returns the number
+//   of rows written
+// - the rows are split into chunks, every chunk has its own random stream
+//   derived from the seed and the chunk number, so the file is the same for
+//   any number of producers
+// - the chunks are dealt out round-robin, producer p makes chunks p, p + n,
+//   p + 2n, ... in order and sends them over its own small bounded channel
+// - the writer takes the chunks from the producers in turn, so they arrive in
+//   file order and a producer that gets ahead blocks instead of piling up
+//   chunks in memory
+pub fn generate_apple_csv<W: Write>(
+    mut out: W,
+    options: &GeneratorOptions,
+) -> Result<u64, Box<dyn Error>> {
+    if options.classes.is_empty() {
+        return Err("at least one class is needed to generate apples".into());
+    }
+    // the ids count up from 0 and A_id is an i32 in AppleQuality
+    if options.rows > i32::MAX as u64 {
+        return Err(format!(
+            "at most {} apples can be generated, A_id is a 32-bit number",
+            i32::MAX
+        )
+        .into());
+    }
+    let weights = WeightedIndex::new(options.classes.iter().map(|class| class.weight))?;
+    let distributions = options
+        .classes
+        .iter()
+        .map(|class| {
+            class
+                .means
+                .iter()
+                .zip(class.std_devs)
+                .map(|(&mean, std_dev)| Normal::new(mean, std_dev))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut header = vec!["A_id"];
+    header.extend(AppleQuality::ATTRIBUTES);
+    header.push("Quality");
+    writeln!(out, "{}", header.join(","))?;
+
+    let chunk_size = options.chunk_size.max(1);
+    let chunks = options.rows.div_ceil(chunk_size);
+    let producers = options.producers.max(1);
+
+    thread::scope(|s| -> Result<(), Box<dyn Error>> {
+        let receivers: Vec<_> = (0..producers)
+            .map(|producer| {
+                let (tx, rx) = channel::bounded::<Vec<u8>>(CHUNKS_BUFFERED_PER_PRODUCER);
+                let (weights, distributions) = (&weights, &distributions);
+                s.spawn(move || {
+                    for chunk in (producer as u64..chunks).step_by(producers) {
+                        let bytes =
+                            generate_chunk(chunk, chunk_size, options, weights, distributions);
+                        // the writer has gone away after an error, so stop producing
+                        if tx.send(bytes).is_err() {
+                            break;
+                        }
+                    }
+                });
+                rx
+            })
+            .collect();
+
+        for chunk in 0..chunks {
+            let bytes = receivers[(chunk % producers as u64) as usize]
+                .recv()
+                .map_err(|_| format!("the producer of chunk {} stopped", chunk))?;
+            out.write_all(&bytes)?;
+        }
+        Ok(())
+    })?;
+
+    out.flush()?;
+    Ok(options.rows)
+}
+
+// how many finished chunks a producer may hold before it waits for the writer
+const CHUNKS_BUFFERED_PER_PRODUCER: usize = 2;
+
+// the csv lines of one chunk, from its own random stream
+fn generate_chunk(
+    chunk: u64,
+    chunk_size: u64,
+    options: &GeneratorOptions,
+    weights: &WeightedIndex<f64>,
+    distributions: &[Vec<Normal<f64>>],
+) -> Vec<u8> {
+    let mut rng = ChaCha8Rng::seed_from_u64(options.seed);
+    rng.set_stream(chunk);
+
+    // the rows were checked to fit in an i32 id
+    let first = i32::try_from(chunk * chunk_size).unwrap();
+    let last = i32::try_from((chunk * chunk_size + chunk_size).min(options.rows)).unwrap();
+    let mut wtr = WriterBuilder::new()
+        .has_headers(false)
+        .from_writer(Vec::new());
+    for id in first..last {
+        let class = weights.sample(&mut rng);
+        let mut record = vec![id.to_string()];
+        record.extend(
+            distributions[class]
+                .iter()
+                .map(|normal| format!("{:.9}", normal.sample(&mut rng))),
+        );
+        record.push(options.classes[class].label.clone());
+        // writing to a vec cannot fail
+        wtr.write_record(&record).unwrap();
+    }
+    wtr.into_inner().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apple_quality::read_apple_quality_csv;
+
+    fn generate(rows: u64, producers: usize, chunk_size: u64) -> Result<String, Box<dyn Error>> {
+        let options = GeneratorOptions {
+            rows,
+            seed: 7,
+            producers,
+            chunk_size,
+            ..GeneratorOptions::default()
+        };
+        let mut out = Vec::new();
+        generate_apple_csv(&mut out, &options)?;
+        Ok(String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn the_file_does_not_depend_on_the_number_of_producers() {
+        let single = generate(1000, 1, 64).unwrap();
+        assert_eq!(generate(1000, 3, 64).unwrap(), single);
+        assert_eq!(generate(1000, 16, 64).unwrap(), single);
+        assert_ne!(generate(1000, 3, 100).unwrap(), single);
+    }
+
+    #[test]
+    fn the_generated_file_reads_back_with_ids_in_order() {
+        let file_path =
+            std::env::temp_dir().join(format!("apple_generated_{}.csv", std::process::id()));
+        std::fs::write(&file_path, generate(250, 4, 16).unwrap()).unwrap();
+        let apples = read_apple_quality_csv(&file_path);
+        std::fs::remove_file(&file_path).unwrap();
+
+        let ids: Vec<i32> = apples.unwrap().iter().map(|apple| apple.id).collect();
+        assert_eq!(ids, (0..250).collect::<Vec<i32>>());
+    }
+
+    #[test]
+    fn more_rows_than_ids_are_rejected() {
+        let error = generate(i32::MAX as u64 + 1, 1, 10).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "at most 2147483647 apples can be generated, A_id is a 32-bit number"
+        );
+    }
+}
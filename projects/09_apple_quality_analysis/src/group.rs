@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::thread;
+
+use crate::apple_quality::{summarize_apples, AppleQuality};
+use crate::filter::Filter;
+use crate::statistics::AttributeSummary;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GroupSummary {
+    pub group: String,
+    pub attributes: Vec<AttributeSummary>,
+}
+
+// the distinct labels of the grouping column, in the order they first appear
+pub fn distinct_labels(apples: &[AppleQuality], group_by: &str) -> Vec<String> {
+    let mut seen = HashSet::new();
+    apples
+        .iter()
+        .filter_map(|apple| apple.group_label(group_by))
+        .filter(|label| seen.insert(label.to_owned()))
+        .map(str::to_owned)
+        .collect()
+}
+
+// - the apples of each distinct label, in the order the labels first appear
+// - with a filter only the matching apples are kept, a label without any is left out
+pub fn group_apples<'a>(
+    apples: &'a [AppleQuality],
+    group_by: &str,
+    filter: Option<&Filter>,
+) -> Vec<(String, Vec<&'a AppleQuality>)> {
+    distinct_labels(apples, group_by)
+        .into_iter()
+        .map(|label| {
+            let group: Vec<&AppleQuality> = apples
+                .iter()
+                .filter(|apple| apple.group_label(group_by) == Some(label.as_str()))
+                .filter(|apple| filter.is_none_or(|filter| filter.matches_apple(apple)))
+                .collect();
+            (label, group)
+        })
+        .filter(|(_, group)| !group.is_empty())
+        .collect()
+}
+
+// - discovers the distinct labels of the grouping column and summarizes each
+//   group on a worker thread
+// - when there are more groups than `threads`, the groups are dealt out
+//   round-robin so no more than `threads` workers are spawned
+// - the summaries are returned in the order the labels first appear
+// - with a filter each worker skips the apples that do not match it
+pub fn summarize_groups(
+    apples: Arc<Vec<AppleQuality>>,
+    group_by: &str,
+    quantiles: &[f64],
+    threads: usize,
+    filter: Option<&Filter>,
+) -> Vec<GroupSummary> {
+    let labels = distinct_labels(&apples, group_by);
+    let group_by = group_by.to_owned();
+    let quantiles = quantiles.to_vec();
+    let filter = filter.cloned();
+
+    summarize_labels(apples, labels, threads, move |apples, label| {
+        let group_apples: Vec<&AppleQuality> = apples
+            .iter()
+            .filter(|apple| apple.group_label(&group_by) == Some(label))
+            .filter(|apple| filter.as_ref().is_none_or(|f| f.matches_apple(apple)))
+            .collect();
+        (!group_apples.is_empty()).then(|| summarize_apples(&group_apples, &quantiles))
+    })
+}
+
+// - the worker pool behind `summarize_groups`, for any kind of row
+// - `summarize` picks the rows of one label out of all rows and summarizes them,
+//   a label that has no rows left after filtering gives none and is left out
+pub(crate) fn summarize_labels<T, F>(
+    rows: Arc<Vec<T>>,
+    labels: Vec<String>,
+    threads: usize,
+    summarize: F,
+) -> Vec<GroupSummary>
+where
+    T: Send + Sync + 'static,
+    F: Fn(&[T], &str) -> Option<Vec<AttributeSummary>> + Send + Sync + 'static,
+{
+    let workers = threads.max(1).min(labels.len());
+    let summarize = Arc::new(summarize);
+
+    let handles: Vec<_> = (0..workers)
+        .map(|worker| {
+            let worker_labels: Vec<String> = labels
+                .iter()
+                .skip(worker)
+                .step_by(workers)
+                .cloned()
+                .collect();
+
+            // each worker gets its own clone of the arc, so the rows are
+            // shared between the threads without copying them
+            let rows = Arc::clone(&rows);
+            let summarize = Arc::clone(&summarize);
+
+            thread::spawn(move || {
+                worker_labels
+                    .into_iter()
+                    .filter_map(|label| {
+                        summarize(&rows, &label).map(|attributes| GroupSummary {
+                            attributes,
+                            group: label,
+                        })
+                    })
+                    .collect::<Vec<GroupSummary>>()
+            })
+        })
+        .collect();
+
+    // wait for the workers, then put the groups back in label order
+    let mut summaries: Vec<GroupSummary> = handles
+        .into_iter()
+        .flat_map(|handle| handle.join().unwrap())
+        .collect();
+    summaries.sort_by_key(|summary| labels.iter().position(|label| *label == summary.group));
+    summaries
+}
+
+// - applies `work` to every item on at most `threads` scoped threads, the
+//   items are dealt out round-robin like the labels of `summarize_groups`
+// - the threads borrow the items, and the results come back in item order
+pub(crate) fn map_on_threads<T, R, F>(items: &[T], threads: usize, work: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = threads.max(1).min(items.len());
+    let mut results: Vec<Option<R>> = items.iter().map(|_| None).collect();
+
+    thread::scope(|s| {
+        let handles: Vec<_> = (0..workers)
+            .map(|worker| {
+                let work = &work;
+                s.spawn(move || {
+                    items
+                        .iter()
+                        .enumerate()
+                        .skip(worker)
+                        .step_by(workers)
+                        .map(|(i, item)| (i, work(item)))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+
+        for handle in handles {
+            for (i, result) in handle.join().unwrap() {
+                results[i] = Some(result);
+            }
+        }
+    });
+
+    results.into_iter().map(Option::unwrap).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apples() -> Arc<Vec<AppleQuality>> {
+        let labels = [
+            "premium", "cull", "premium", "unknown", "good", "cull", "good",
+        ];
+        let apples = (0..70)
+            .map(|id| {
+                let x = id as f64;
+                let values = [x, -x, x * 0.5, (x * 0.3).sin(), x % 4.0, 1.0, x * x];
+                AppleQuality::from_values(id, values, labels[id as usize % labels.len()])
+            })
+            .collect();
+        Arc::new(apples)
+    }
+
+    #[test]
+    fn labels_follow_their_first_appearance() {
+        assert_eq!(
+            distinct_labels(&apples(), "quality"),
+            ["premium", "cull", "unknown", "good"]
+        );
+        assert!(distinct_labels(&apples(), "colour").is_empty());
+    }
+
+    #[test]
+    fn the_summaries_do_not_depend_on_the_number_of_threads() {
+        let apples = apples();
+        let one = summarize_groups(Arc::clone(&apples), "Quality", &[0.25], 1, None);
+
+        let groups: Vec<&str> = one.iter().map(|summary| summary.group.as_str()).collect();
+        assert_eq!(groups, ["premium", "cull", "unknown", "good"]);
+        assert_eq!(one[0].attributes[0].count, 20);
+        assert_eq!(one[2].attributes[0].count, 10);
+        for threads in [0, 2, 3, 4, 16] {
+            assert_eq!(
+                summarize_groups(Arc::clone(&apples), "Quality", &[0.25], threads, None),
+                one,
+                "{} threads",
+                threads
+            );
+        }
+    }
+
+    #[test]
+    fn mapped_results_keep_the_item_order() {
+        let items: Vec<usize> = (0..10).collect();
+        for threads in [0, 1, 3, 10, 20] {
+            let squares = map_on_threads(&items, threads, |&i| i * i);
+            assert_eq!(squares, [0, 1, 4, 9, 16, 25, 36, 49, 64, 81]);
+        }
+        assert!(map_on_threads(&[] as &[usize], 4, |&i| i).is_empty());
+    }
+
+    #[test]
+    fn a_group_emptied_by_the_filter_is_left_out() {
+        let filter = Filter::parse_apple("quality != cull and size < 60").unwrap();
+        let summaries = summarize_groups(apples(), "Quality", &[], 3, Some(&filter));
+
+        let groups: Vec<&str> = summaries
+            .iter()
+            .map(|summary| summary.group.as_str())
+            .collect();
+        assert_eq!(groups, ["premium", "unknown", "good"]);
+        assert!(summaries
+            .iter()
+            .all(|summary| summary.attributes[0].max < 60.0));
+    }
+}
@@ -0,0 +1,222 @@
+use crate::apple_quality::AppleQuality;
+use crate::group::map_on_threads;
+use crate::report::{ReportRow, Section};
+
+// the longest bar drawn in a text histogram
+const BAR_WIDTH: usize = 30;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Histogram {
+    pub attribute: String,
+    pub group: String,
+    // bins + 1 edges, bin i covers [edges[i], edges[i + 1]) and the last bin
+    // also includes its upper edge
+    pub edges: Vec<f64>,
+    pub counts: Vec<usize>,
+}
+
+// the edges of `bins` equally wide bins between min and max
+fn bin_edges(min: f64, max: f64, bins: usize) -> Vec<f64> {
+    let width = (max - min) / bins as f64;
+    (0..=bins).map(|i| min + width * i as f64).collect()
+}
+
+// - a value is counted in the bin whose edges surround it, comparing it with
+//   the edges themselves so a value on an edge always opens the next bin
+// - the max is counted in the last bin, and when every value is the same the
+//   edges coincide and they all land there too
+fn bin_counts(values: impl Iterator<Item = f64>, edges: &[f64]) -> Vec<usize> {
+    let bins = edges.len() - 1;
+    let mut counts = vec![0; bins];
+    for value in values {
+        let bin = edges[1..bins].partition_point(|&edge| edge <= value);
+        counts[bin] += 1;
+    }
+    counts
+}
+
+// - bins every attribute of every group into `bins` equally wide bins
+// - the bins of an attribute span its range over all groups, so the shapes of
+//   the groups can be compared bin by bin
+// - the groups are counted on at most `threads` scoped threads
+// - returns one vector of histograms per attribute, with one histogram per group
+pub fn histograms(
+    groups: &[(String, Vec<&AppleQuality>)],
+    bins: usize,
+    threads: usize,
+) -> Vec<Vec<Histogram>> {
+    let bins = bins.max(1);
+
+    let edges: Vec<Vec<f64>> = (0..AppleQuality::ATTRIBUTES.len())
+        .map(|i| {
+            let values = groups.iter().flat_map(|(_, apples)| {
+                apples.iter().map(move |apple| apple.attribute_values()[i])
+            });
+            let (min, max) = values
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+                    (min.min(value), max.max(value))
+                });
+            bin_edges(min, max, bins)
+        })
+        .collect();
+
+    let per_group: Vec<Vec<Histogram>> = map_on_threads(groups, threads, |(group, apples)| {
+        AppleQuality::ATTRIBUTES
+            .iter()
+            .zip(&edges)
+            .enumerate()
+            .map(|(i, (attribute, edges))| Histogram {
+                attribute: attribute.to_string(),
+                group: group.clone(),
+                edges: edges.clone(),
+                counts: bin_counts(
+                    apples.iter().map(|apple| apple.attribute_values()[i]),
+                    edges,
+                ),
+            })
+            .collect()
+    });
+
+    // regroup from one vector per group into one vector per attribute
+    (0..AppleQuality::ATTRIBUTES.len())
+        .map(|i| per_group.iter().map(|group| group[i].clone()).collect())
+        .collect()
+}
+
+fn bin_label(edges: &[f64], bin: usize) -> String {
+    let close = if bin + 2 == edges.len() { "]" } else { ")" };
+    format!("[{:.2}, {:.2}{}", edges[bin], edges[bin + 1], close)
+}
+
+// - one section per attribute with one text histogram column per group
+// - the bars are scaled to the largest bin of the attribute over all groups
+pub fn histogram_section(histograms: &[Histogram]) -> Section {
+    let mut header = vec!["Bin".to_owned()];
+    header.extend(histograms.iter().map(|histogram| histogram.group.clone()));
+
+    let largest = histograms
+        .iter()
+        .flat_map(|histogram| histogram.counts.iter().copied())
+        .max()
+        .unwrap_or(0)
+        .max(1);
+    let edges = histograms
+        .first()
+        .map_or(&[][..], |histogram| &histogram.edges[..]);
+
+    let rows = (0..edges.len().saturating_sub(1))
+        .map(|bin| {
+            let mut row = vec![bin_label(edges, bin)];
+            row.extend(histograms.iter().map(|histogram| {
+                let count = histogram.counts[bin];
+                format!("{:>5} {}", count, "#".repeat(count * BAR_WIDTH / largest))
+            }));
+            row
+        })
+        .collect();
+
+    Section {
+        title: format!(
+            "Histogram of {}",
+            histograms
+                .first()
+                .map_or("", |histogram| &histogram.attribute)
+        ),
+        header,
+        rows,
+    }
+}
+
+impl Histogram {
+    // one row per bin, e.g. (Size, good, bin_03[-1.4300,0.0012), 512)
+    pub fn report_rows(&self) -> Vec<ReportRow> {
+        self.counts
+            .iter()
+            .enumerate()
+            .map(|(bin, &count)| ReportRow {
+                attribute: self.attribute.clone(),
+                group: self.group.clone(),
+                statistic: format!(
+                    "bin_{:02}[{:.4},{:.4}{}",
+                    bin,
+                    self.edges[bin],
+                    self.edges[bin + 1],
+                    if bin + 2 == self.edges.len() {
+                        "]"
+                    } else {
+                        ")"
+                    }
+                ),
+                value: count as f64,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apples_with_sizes(sizes: &[f64], quality: &str) -> Vec<AppleQuality> {
+        sizes
+            .iter()
+            .enumerate()
+            .map(|(id, &size)| AppleQuality::from_values(id as i32, [size; 7], quality))
+            .collect()
+    }
+
+    #[test]
+    fn values_on_an_edge_open_the_next_bin_and_the_max_closes_the_last() {
+        // the edges at 0.1 steps are not exact, e.g. the fourth is 0.30000000000000004
+        let edges = bin_edges(0.0, 1.0, 10);
+        let bin_of = |value: f64| {
+            let counts = bin_counts(std::iter::once(value), &edges);
+            counts.iter().position(|&count| count == 1).unwrap()
+        };
+
+        for (i, &edge) in edges.iter().enumerate() {
+            assert_eq!(bin_of(edge), i.min(9), "edge {}", edge);
+        }
+        assert_eq!(bin_of(0.3), 2);
+        assert_eq!(bin_of(0.2999), 2);
+        assert_eq!(bin_of(0.95), 9);
+        assert_eq!(
+            bin_counts([0.0, 0.05, 0.5, 1.0, 1.0].into_iter(), &edges),
+            [2, 0, 0, 0, 0, 1, 0, 0, 0, 2]
+        );
+    }
+
+    #[test]
+    fn a_column_without_spread_lands_in_one_bin() {
+        let good = apples_with_sizes(&[2.5, 2.5, 2.5], "good");
+        let groups = vec![("good".to_owned(), good.iter().collect())];
+
+        let histograms = histograms(&groups, 4, 2);
+        let size = &histograms[0][0];
+        assert_eq!(size.edges, [2.5; 5]);
+        assert_eq!(size.counts, [0, 0, 0, 3]);
+    }
+
+    #[test]
+    fn the_groups_share_edges_and_count_every_row() {
+        let good = apples_with_sizes(&[0.0, 1.0, 2.0, 3.0, 4.0], "good");
+        let bad = apples_with_sizes(&[-4.0, -2.0, 0.0], "bad");
+        let groups = vec![
+            ("good".to_owned(), good.iter().collect()),
+            ("bad".to_owned(), bad.iter().collect()),
+        ];
+
+        let histograms = histograms(&groups, 4, 2);
+        assert_eq!(histograms.len(), AppleQuality::ATTRIBUTES.len());
+        let size = &histograms[0];
+        assert_eq!(size[0].edges, [-4.0, -2.0, 0.0, 2.0, 4.0]);
+        assert_eq!(size[1].edges, size[0].edges);
+        assert_eq!(size[0].counts, [0, 0, 2, 3]);
+        assert_eq!(size[1].counts, [1, 1, 1, 0]);
+        assert_eq!(super::histograms(&groups, 4, 1), histograms);
+        for histogram in histograms.iter().flatten() {
+            let rows = if histogram.group == "good" { 5 } else { 3 };
+            assert_eq!(histogram.counts.iter().sum::<usize>(), rows);
+        }
+    }
+}
@@ -0,0 +1,53 @@
+pub mod aggregate;
+pub mod apple_quality;
+pub mod classifier;
+pub mod correlation;
+pub mod filter;
+pub mod generator;
+pub mod group;
+pub mod histogram;
+pub mod outliers;
+pub mod pipeline;
+pub mod report;
+pub mod scaling;
+pub mod schema;
+pub mod significance;
+pub mod sketch;
+pub mod statistics;
+pub mod strategies;
+pub mod validation;
+
+pub use crate::aggregate::{AggregateState, AttributeAggregate, BatchId, GroupAggregates};
+pub use crate::apple_quality::{read_apple_quality_csv, summarize_apples, AppleQuality};
+pub use crate::classifier::{
+    train_and_evaluate, ClassifierOptions, ClassifierReport, ConfusionMatrix, LogisticRegression,
+};
+pub use crate::correlation::{correlation_matrix, pearson, CorrelationMatrix};
+pub use crate::filter::{Comparison, Filter};
+pub use crate::generator::{generate_apple_csv, ClassDistribution, GeneratorOptions};
+pub use crate::group::{distinct_labels, group_apples, summarize_groups, GroupSummary};
+pub use crate::histogram::{histogram_section, histograms, Histogram};
+pub use crate::outliers::{
+    find_outliers, outlier_report_rows, outlier_section, write_outliers_csv, Outlier,
+    OutlierOptions, OutlierRule,
+};
+pub use crate::pipeline::{
+    aggregate_csv_streaming, aggregate_schema_csv_streaming, summarize_csv_streaming,
+    summarize_schema_csv_streaming, PipelineOptions,
+};
+pub use crate::report::{
+    display_comparative_summary_in_table, report_rows, write_csv, write_json, write_markdown,
+    ReportRow, Section,
+};
+pub use crate::scaling::{write_scaled_csv, AttributeScaling, ScalingMethod, ScalingParameters};
+pub use crate::schema::{
+    distinct_record_labels, read_records_csv, summarize_records, Record, RecordParser, Schema,
+};
+pub use crate::significance::{mann_whitney_tests, welch_tests, PairTest, TestKind};
+pub use crate::sketch::QuantileSketch;
+pub use crate::statistics::{AttributeSummary, DEFAULT_QUANTILES};
+pub use crate::strategies::{summarize_with, Strategy};
+pub use crate::validation::{
+    display_rejection_summary_in_table, read_apple_quality_csv_lenient, RejectionReport, RowError,
+    ValidationOptions,
+};
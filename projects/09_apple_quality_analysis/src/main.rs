@@ -1,47 +1,611 @@
-#[macro_use]
-extern crate prettytable;
-
-mod apple_quality;
-
-use crate::apple_quality::{
-    display_comparative_summary_in_table, read_apple_quality_csv, summarize_apples, AppleQuality,
+use apple_quality_analysis::{
+    aggregate_csv_streaming, aggregate_schema_csv_streaming, correlation_matrix,
+    display_comparative_summary_in_table, display_rejection_summary_in_table, find_outliers,
+    group_apples, histogram_section, histograms, mann_whitney_tests, outlier_report_rows,
+    outlier_section, read_apple_quality_csv, read_apple_quality_csv_lenient, read_records_csv,
+    report_rows, summarize_csv_streaming, summarize_groups, summarize_records,
+    summarize_schema_csv_streaming, train_and_evaluate, welch_tests, write_csv, write_json,
+    write_markdown, write_outliers_csv, write_scaled_csv, AggregateState, AppleQuality, BatchId,
+    ClassifierOptions, CorrelationMatrix, Filter, GroupSummary, Histogram, OutlierOptions,
+    PairTest, PipelineOptions, RejectionReport, ScalingMethod, ScalingParameters, Schema, Section,
+    ValidationOptions, DEFAULT_QUANTILES,
 };
+use clap::builder::RangedU64ValueParser;
+use clap::{Args as ClapArgs, Parser, Subcommand, ValueEnum};
+use std::error::Error;
+use std::io::{self, Write};
+use std::path::Path;
+use std::process::ExitCode;
 use std::sync::Arc;
 use std::thread;
 
-fn main() {
-    let file_path = "../data/apple_quality.csv";
-    let apple_quality = read_apple_quality_csv(file_path).unwrap();
-    let apple_quality = Arc::new(apple_quality);
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum OutputFormat {
+    Table,
+    Json,
+    Csv,
+    Markdown,
+}
 
-    let apple_quality_good = Arc::clone(&apple_quality);
-    let handle_good = thread::spawn(move || {
-        let good_apples: Vec<&AppleQuality> = apple_quality_good
-            .iter()
-            .filter(|x| x.quality == "good")
-            .collect();
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum CorrelationScope {
+    All,
+    PerGroup,
+}
+
+#[derive(Debug, Parser)]
+#[command(about = "Compare the attributes of apples, or of any CSV file, across groups")]
+struct Args {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Path to the CSV file, in the apple quality layout unless --schema is given
+    #[arg(
+        short,
+        long,
+        global = true,
+        default_value = "../data/apple_quality.csv"
+    )]
+    input: String,
+
+    /// Column used to split the rows into groups, defaults to Quality, or
+    /// with --schema auto to the first text column
+    #[arg(short, long)]
+    group_by: Option<String>,
+
+    /// Only summarize the rows matching this expression, e.g.
+    /// "ripeness > 1.0 and acidity < 0" or "not (quality == bad or size <= -2)",
+    /// column names with spaces or a leading digit are quoted, e.g.
+    /// "'fruit size' > 1"
+    #[arg(long, value_name = "EXPRESSION")]
+    filter: Option<String>,
+
+    /// Read any CSV layout through a schema, either discovered from the file
+    /// with "auto" or loaded from a JSON config file
+    #[arg(
+        long,
+        value_name = "auto|PATH",
+        conflicts_with_all = ["correlation", "histogram", "outliers", "outliers_out"]
+    )]
+    schema: Option<String>,
+
+    /// Format of the comparative summary
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table)]
+    format: OutputFormat,
+
+    /// Quantiles reported next to the median, between 0 and 1
+    #[arg(
+        short,
+        long,
+        value_delimiter = ',',
+        value_parser = parse_fraction,
+        default_values_t = DEFAULT_QUANTILES
+    )]
+    quantiles: Vec<f64>,
+
+    /// Maximum number of worker threads, defaults to the available parallelism
+    #[arg(short, long, default_value_t = default_threads())]
+    threads: usize,
+
+    /// Stream the file through a reader thread and a pool of workers instead
+    /// of loading it into memory, the median and the quantiles are then
+    /// estimated with a quantile sketch and labelled "(est.)"
+    #[arg(long)]
+    streaming: bool,
+
+    /// Merge the aggregates of the input into this state file, creating it
+    /// when missing, and report on every file merged into it so far, the
+    /// median and the quantiles are estimated and labelled "(est.)", a file
+    /// already merged is rejected, and so is a --filter other than the one
+    /// the state was built with
+    #[arg(
+        long,
+        conflicts_with_all = ["correlation", "histogram", "outliers", "outliers_out"]
+    )]
+    state: Option<String>,
+
+    /// Number of records per batch sent to the workers in streaming mode
+    #[arg(long, default_value_t = PipelineOptions::default().batch_size)]
+    batch_size: usize,
+
+    /// Number of batches buffered between the reader and the workers in
+    /// streaming mode
+    #[arg(long, default_value_t = PipelineOptions::default().channel_capacity)]
+    channel_capacity: usize,
+
+    /// Skip and report invalid rows instead of failing on the first one
+    #[arg(long)]
+    lenient: bool,
+
+    /// Group labels accepted in lenient mode, any label is accepted when
+    /// not given
+    #[arg(long, value_delimiter = ',', requires = "lenient")]
+    allowed_labels: Option<Vec<String>>,
+
+    /// Write every rejected row to this CSV file in lenient mode
+    #[arg(long, requires = "lenient")]
+    rejections_out: Option<String>,
+
+    /// Also test every attribute between every pair of groups with Welch's
+    /// t-test, shown next to the means
+    #[arg(long)]
+    significance: bool,
+
+    /// Also test every attribute between every pair of groups with the
+    /// Mann-Whitney U test, shown next to the medians
+    #[arg(
+        long,
+        conflicts_with_all = ["streaming", "state", "schema"]
+    )]
+    mann_whitney: bool,
+
+    /// Also compute the correlation matrix of the attributes, across all
+    /// apples or separately for each group
+    #[arg(
+        long,
+        value_enum,
+        num_args = 0..=1,
+        default_missing_value = "all",
+        conflicts_with = "streaming"
+    )]
+    correlation: Option<CorrelationScope>,
+
+    /// Also bin each attribute into this many bins per group and draw text
+    /// histograms
+    #[arg(
+        long,
+        num_args = 0..=1,
+        default_missing_value = "10",
+        conflicts_with = "streaming"
+    )]
+    histogram: Option<usize>,
+
+    /// Also flag outlier values per attribute and group, and show how many
+    /// were found
+    #[arg(long, conflicts_with = "streaming")]
+    outliers: bool,
+
+    /// Write every flagged value with its A_id, rule and score to this CSV file
+    #[arg(long, conflicts_with = "streaming")]
+    outliers_out: Option<String>,
+
+    /// Number of standard deviations from the group mean that flags a value
+    #[arg(long, default_value_t = OutlierOptions::default().z_threshold)]
+    z_threshold: f64,
+
+    /// Number of interquartile ranges outside the quartiles that flags a value
+    #[arg(long, default_value_t = OutlierOptions::default().iqr_multiplier)]
+    iqr_multiplier: f64,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Train a logistic regression classifier and evaluate it on a hold-out
+    /// split and with k-fold cross-validation
+    Classify(ClassifyArgs),
+
+    /// Scale every attribute with min-max or z-score scaling and write the
+    /// scaled rows to a new CSV file
+    Scale(ScaleArgs),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+enum ScaleMethod {
+    MinMax,
+    ZScore,
+}
+
+#[derive(Debug, ClapArgs)]
+struct ScaleArgs {
+    /// Path of the scaled CSV file, with the original A_id and Quality
+    #[arg(short, long)]
+    output: String,
+
+    /// Scaling fitted on the input
+    #[arg(long, value_enum, default_value_t = ScaleMethod::ZScore)]
+    method: ScaleMethod,
+
+    /// Save the fitted parameters as JSON, so later batches can be scaled
+    /// the same way
+    #[arg(long, conflicts_with = "params")]
+    params_out: Option<String>,
+
+    /// Scale with parameters saved by --params-out instead of fitting them on
+    /// the input
+    #[arg(long, conflicts_with = "method")]
+    params: Option<String>,
+}
+
+#[derive(Debug, ClapArgs)]
+struct ClassifyArgs {
+    /// Quality label predicted as positive
+    #[arg(long, default_value_t = ClassifierOptions::default().positive_label)]
+    positive: String,
 
-        // the return value from the closure is the value that will be
-        // returned from the thread when called join
-        summarize_apples(&good_apples)
+    /// Quality label predicted as negative, rows with other labels are left out
+    #[arg(long, default_value_t = ClassifierOptions::default().negative_label)]
+    negative: String,
+
+    /// Share of the rows held out for the final evaluation
+    #[arg(long, value_parser = parse_fraction, default_value_t = ClassifierOptions::default().test_fraction)]
+    test_fraction: f64,
+
+    /// Number of cross-validation folds, at least 2, trained on up to
+    /// --threads threads next to the final model
+    #[arg(
+        long,
+        value_parser = RangedU64ValueParser::<usize>::new().range(2..),
+        default_value_t = ClassifierOptions::default().folds
+    )]
+    folds: usize,
+
+    /// Number of gradient descent passes over the training rows
+    #[arg(long, default_value_t = ClassifierOptions::default().epochs)]
+    epochs: usize,
+
+    /// Gradient descent step size
+    #[arg(long, default_value_t = ClassifierOptions::default().learning_rate)]
+    learning_rate: f64,
+
+    /// Seed for shuffling the rows before they are split
+    #[arg(long, default_value_t = ClassifierOptions::default().seed)]
+    seed: u64,
+}
+
+fn run_classify(input: &str, threads: usize, args: &ClassifyArgs) -> Result<(), Box<dyn Error>> {
+    let apple_quality = read_apple_quality_csv(input)?;
+    let options = ClassifierOptions {
+        positive_label: args.positive.clone(),
+        negative_label: args.negative.clone(),
+        test_fraction: args.test_fraction,
+        folds: args.folds,
+        threads,
+        epochs: args.epochs,
+        learning_rate: args.learning_rate,
+        seed: args.seed,
+    };
+
+    let report = train_and_evaluate(&apple_quality, &options)?;
+    for section in report.sections(&options) {
+        section.display_in_table();
+    }
+
+    Ok(())
+}
+
+// the summary of a csv file in any layout, streamed or loaded into memory like
+// the apple quality file
+fn summarize_with_schema(
+    args: &Args,
+    schema: &Schema,
+    validation: Option<ValidationOptions>,
+    filter: Option<&Filter>,
+) -> Result<(Vec<GroupSummary>, RejectionReport), Box<dyn Error>> {
+    if args.streaming {
+        let options = pipeline_options(args, validation, filter.cloned());
+        return summarize_schema_csv_streaming(&args.input, schema, &args.quantiles, &options);
+    }
+
+    let (records, report) = read_records_csv(&args.input, schema, validation)?;
+    let summaries = summarize_records(
+        Arc::new(records),
+        schema,
+        &args.quantiles,
+        args.threads,
+        filter,
+    );
+    Ok((summaries, report))
+}
+
+fn pipeline_options(
+    args: &Args,
+    validation: Option<ValidationOptions>,
+    filter: Option<Filter>,
+) -> PipelineOptions {
+    PipelineOptions {
+        workers: args.threads,
+        batch_size: args.batch_size,
+        channel_capacity: args.channel_capacity,
+        validation,
+        filter,
+    }
+}
+
+fn run_scale(input: &str, threads: usize, args: &ScaleArgs) -> Result<(), Box<dyn Error>> {
+    let apple_quality = read_apple_quality_csv(input)?;
+
+    let parameters = match &args.params {
+        Some(params) => ScalingParameters::load(params)?,
+        None => {
+            let method = match args.method {
+                ScaleMethod::MinMax => ScalingMethod::MinMax,
+                ScaleMethod::ZScore => ScalingMethod::ZScore,
+            };
+            ScalingParameters::fit(&apple_quality, method, threads)?
+        }
+    };
+    if let Some(params_out) = &args.params_out {
+        parameters.save(params_out)?;
+    }
+
+    write_scaled_csv(&apple_quality, &parameters, &args.output)?;
+    parameters.section().display_in_table();
+    println!(
+        "Wrote {} scaled rows to {}",
+        apple_quality.len(),
+        args.output
+    );
+
+    Ok(())
+}
+
+fn default_threads() -> usize {
+    thread::available_parallelism().map_or(1, |threads| threads.get())
+}
+
+fn parse_fraction(value: &str) -> Result<f64, String> {
+    let q: f64 = value
+        .parse()
+        .map_err(|_| format!("{:?} is not a number", value))?;
+    if q > 0.0 && q < 1.0 {
+        Ok(q)
+    } else {
+        Err(format!("{} is not between 0 and 1", q))
+    }
+}
+
+// - returning the error from main would print it with Debug, quoted and
+//   escaped, so it is printed with Display instead
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(args: Args) -> Result<(), Box<dyn Error>> {
+    match &args.command {
+        Some(Command::Classify(classify)) => {
+            return run_classify(&args.input, args.threads, classify)
+        }
+        Some(Command::Scale(scale)) => return run_scale(&args.input, args.threads, scale),
+        None => {}
+    }
+
+    let validation = args.lenient.then(|| ValidationOptions {
+        allowed_labels: args
+            .allowed_labels
+            .clone()
+            .map(|labels| labels.into_iter().collect()),
     });
 
-    let apple_quality_bad = Arc::clone(&apple_quality);
-    let handle_bad = thread::spawn(move || {
-        let bad_apples: Vec<&AppleQuality> = apple_quality_bad
+    let group_by = args
+        .group_by
+        .clone()
+        .unwrap_or_else(|| AppleQuality::GROUP_COLUMNS[0].to_owned());
+
+    let schema = match args.schema.as_deref() {
+        Some("auto") => Some(Schema::discover(&args.input, args.group_by.as_deref())?),
+        Some(config) => Some(Schema::load(config, args.group_by.as_deref())?),
+        None => None,
+    };
+    // the column names in the filter are resolved against the layout of the input
+    let filter = match (&args.filter, &schema) {
+        (Some(text), Some(schema)) => Some(Filter::parse(
+            text,
+            &schema.numeric_columns,
+            &schema.group_column,
+        )?),
+        (Some(text), None) => Some(Filter::parse_apple(text)?),
+        (None, _) => None,
+    };
+
+    if schema.is_none()
+        && !AppleQuality::GROUP_COLUMNS
             .iter()
-            .filter(|x| x.quality == "bad")
+            .any(|column| column.eq_ignore_ascii_case(&group_by))
+    {
+        return Err(format!(
+            "cannot group by {:?}, expected one of {:?}, or use --schema for other columns",
+            group_by,
+            AppleQuality::GROUP_COLUMNS
+        )
+        .into());
+    }
+
+    let (summaries, report, apple_quality) = if let Some(state_path) = &args.state {
+        // only the new file is read, its aggregates are merged into the saved ones
+        let saved = if Path::new(state_path).exists() {
+            Some(AggregateState::load(state_path)?)
+        } else {
+            None
+        };
+        // a file merged before is turned away before it is read again
+        let batch_id = BatchId::of_file(&args.input)?;
+        if let Some(merged) = saved.as_ref().and_then(|saved| saved.find_batch(&batch_id)) {
+            return Err(format!(
+                "{} was already merged into {} as {}",
+                args.input, state_path, merged.path
+            )
+            .into());
+        }
+
+        let options = pipeline_options(&args, validation, filter.clone());
+        let (mut batch, report) = match &schema {
+            Some(schema) => aggregate_schema_csv_streaming(&args.input, schema, &options)?,
+            None => aggregate_csv_streaming(&args.input, &group_by, &options)?,
+        };
+        batch.batches.push(batch_id);
+        batch.filter = args.filter.clone();
+        let state = match saved {
+            Some(mut state) => {
+                state.merge(&batch)?;
+                state
+            }
+            None => batch,
+        };
+        state.save(state_path)?;
+        (state.summaries(&args.quantiles), report, None)
+    } else if let Some(schema) = &schema {
+        let (summaries, report) =
+            summarize_with_schema(&args, schema, validation, filter.as_ref())?;
+        (summaries, report, None)
+    } else if args.streaming {
+        // a reader thread feeds a pool of --threads workers through a bounded channel
+        let options = pipeline_options(&args, validation, filter.clone());
+        let (summaries, report) =
+            summarize_csv_streaming(&args.input, &group_by, &args.quantiles, &options)?;
+        (summaries, report, None)
+    } else {
+        let (apple_quality, report) = match validation {
+            Some(validation) => read_apple_quality_csv_lenient(&args.input, validation)?,
+            None => {
+                let apple_quality = read_apple_quality_csv(&args.input)?;
+                let report = RejectionReport {
+                    rows_read: apple_quality.len(),
+                    ..RejectionReport::default()
+                };
+                (apple_quality, report)
+            }
+        };
+        let apple_quality = Arc::new(apple_quality);
+
+        // one worker per distinct label of the grouping column, bounded by --threads
+        let summaries = summarize_groups(
+            Arc::clone(&apple_quality),
+            &group_by,
+            &args.quantiles,
+            args.threads,
+            filter.as_ref(),
+        );
+        (summaries, report, Some(apple_quality))
+    };
+
+    // the parts of the report that need every apple in memory
+    let mut matrices = Vec::new();
+    if let (Some(scope), Some(apple_quality)) = (args.correlation, &apple_quality) {
+        let apples: Vec<&AppleQuality> = apple_quality
+            .iter()
+            .filter(|apple| filter.as_ref().is_none_or(|f| f.matches_apple(apple)))
             .collect();
+        match scope {
+            CorrelationScope::All => {
+                matrices.push(correlation_matrix("all", &apples, args.threads));
+            }
+            CorrelationScope::PerGroup => {
+                for (label, group_apples) in group_apples(apple_quality, &group_by, filter.as_ref())
+                {
+                    matrices.push(correlation_matrix(&label, &group_apples, args.threads));
+                }
+            }
+        }
+    }
+    let mut attribute_histograms = Vec::new();
+    if let (Some(bins), Some(apple_quality)) = (args.histogram, &apple_quality) {
+        attribute_histograms = histograms(
+            &group_apples(apple_quality, &group_by, filter.as_ref()),
+            bins,
+            args.threads,
+        );
+    }
 
-        // the return value from the closure is the value that will be
-        // returned from the thread when called join
-        summarize_apples(&bad_apples)
-    });
+    let mut outliers = None;
+    if let Some(apple_quality) = &apple_quality {
+        if args.outliers || args.outliers_out.is_some() {
+            let options = OutlierOptions {
+                z_threshold: args.z_threshold,
+                iqr_multiplier: args.iqr_multiplier,
+            };
+            outliers = Some(find_outliers(
+                &group_apples(apple_quality, &group_by, filter.as_ref()),
+                options,
+                args.threads,
+            ));
+        }
+    }
+    if let (Some(outliers), Some(outliers_out)) = (&outliers, &args.outliers_out) {
+        write_outliers_csv(outliers, outliers_out)?;
+    }
+
+    let mut tests = Vec::new();
+    if args.significance {
+        tests.extend(welch_tests(&summaries));
+    }
+    if let (true, Some(apple_quality)) = (args.mann_whitney, &apple_quality) {
+        tests.extend(mann_whitney_tests(
+            &group_apples(apple_quality, &group_by, filter.as_ref()),
+            args.threads,
+        ));
+    }
 
-    // wait for threads to finish and collect summaries
-    let good_summary = handle_good.join().unwrap();
-    let bad_summary = handle_bad.join().unwrap();
+    let mut sections: Vec<Section> = matrices.iter().map(CorrelationMatrix::section).collect();
+    sections.extend(
+        attribute_histograms
+            .iter()
+            .map(|histograms| histogram_section(histograms)),
+    );
+    sections.extend(outliers.as_deref().map(outlier_section));
 
     // using the updated function to print a comparative summary
-    display_comparative_summary_in_table(&good_summary, &bad_summary);
+    // json and csv use the long (attribute, group, statistic, value) schema
+    // the other parts of the report follow as extra sections or extra rows
+    match args.format {
+        OutputFormat::Table => {
+            display_comparative_summary_in_table(&summaries, &tests);
+            for section in &sections {
+                section.display_in_table();
+            }
+        }
+        OutputFormat::Markdown => {
+            let mut out = io::stdout().lock();
+            write_markdown(&summaries, &tests, &mut out)?;
+            for section in &sections {
+                writeln!(out)?;
+                section.write_markdown(&mut out)?;
+            }
+        }
+        OutputFormat::Json | OutputFormat::Csv => {
+            let mut rows = report_rows(&summaries);
+            rows.extend(tests.iter().flat_map(PairTest::report_rows));
+            rows.extend(matrices.iter().flat_map(CorrelationMatrix::report_rows));
+            rows.extend(
+                attribute_histograms
+                    .iter()
+                    .flatten()
+                    .flat_map(Histogram::report_rows),
+            );
+            rows.extend(
+                outliers
+                    .as_deref()
+                    .map(outlier_report_rows)
+                    .unwrap_or_default(),
+            );
+            if args.format == OutputFormat::Json {
+                write_json(&rows, io::stdout().lock())?;
+            } else {
+                write_csv(&rows, io::stdout().lock())?;
+            }
+        }
+    }
+
+    // machine-readable output keeps stdout clean, the rejections go to stderr
+    // and to --rejections-out
+    if args.lenient {
+        if args.format == OutputFormat::Table {
+            display_rejection_summary_in_table(&report);
+        } else {
+            eprintln!(
+                "Rejected {} of {} rows",
+                report.rows_rejected, report.rows_read
+            );
+        }
+    }
+    if let Some(rejections_out) = &args.rejections_out {
+        report.write_csv(rejections_out)?;
+    }
+
+    Ok(())
 }
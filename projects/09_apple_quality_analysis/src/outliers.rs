@@ -0,0 +1,298 @@
+use csv::Writer;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::Path;
+
+use crate::apple_quality::AppleQuality;
+use crate::group::map_on_threads;
+use crate::report::{ReportRow, Section};
+use crate::statistics::quantile;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct OutlierOptions {
+    // a value more than this many standard deviations from the group mean is flagged
+    pub z_threshold: f64,
+    // a value more than this many interquartile ranges outside the quartiles is flagged
+    pub iqr_multiplier: f64,
+}
+
+impl Default for OutlierOptions {
+    fn default() -> Self {
+        OutlierOptions {
+            z_threshold: 3.0,
+            iqr_multiplier: 1.5,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlierRule {
+    ZScore,
+    Iqr,
+}
+
+impl OutlierRule {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutlierRule::ZScore => "z_score",
+            OutlierRule::Iqr => "iqr",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Outlier {
+    #[serde(rename = "A_id")]
+    pub id: i32,
+    pub group: String,
+    pub attribute: String,
+    pub rule: OutlierRule,
+    pub value: f64,
+    // - z-score rule: the number of standard deviations from the mean
+    // - iqr rule: the number of interquartile ranges beyond the nearest quartile
+    pub score: f64,
+}
+
+fn group_outliers(group: &str, apples: &[&AppleQuality], options: OutlierOptions) -> Vec<Outlier> {
+    let mut outliers = Vec::new();
+
+    for (i, attribute) in AppleQuality::ATTRIBUTES.iter().enumerate() {
+        let values: Vec<f64> = apples
+            .iter()
+            .map(|apple| apple.attribute_values()[i])
+            .collect();
+        let n = values.len() as f64;
+        if values.len() < 2 {
+            continue;
+        }
+
+        let mean = values.iter().sum::<f64>() / n;
+        let std_dev = (values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0)).sqrt();
+
+        let mut sorted = values.clone();
+        sorted.sort_by(f64::total_cmp);
+        let q1 = quantile(&sorted, 0.25);
+        let q3 = quantile(&sorted, 0.75);
+        let iqr = q3 - q1;
+
+        for (apple, &value) in apples.iter().zip(&values) {
+            let mut flag = |rule, score| {
+                outliers.push(Outlier {
+                    id: apple.id,
+                    group: group.to_owned(),
+                    attribute: attribute.to_string(),
+                    rule,
+                    value,
+                    score,
+                })
+            };
+
+            if std_dev > 0.0 {
+                let z = (value - mean) / std_dev;
+                if z.abs() > options.z_threshold {
+                    flag(OutlierRule::ZScore, z);
+                }
+            }
+
+            if iqr > 0.0 {
+                let beyond = if value < q1 {
+                    (value - q1) / iqr
+                } else {
+                    (value - q3).max(0.0) / iqr
+                };
+                if beyond.abs() > options.iqr_multiplier {
+                    flag(OutlierRule::Iqr, beyond);
+                }
+            }
+        }
+    }
+
+    outliers
+}
+
+// - flags the values of each attribute that break the z-score or the iqr rule
+//   of their group, the groups are checked on at most `threads` scoped threads
+// - the outliers are sorted by id, attribute and rule
+pub fn find_outliers(
+    groups: &[(String, Vec<&AppleQuality>)],
+    options: OutlierOptions,
+    threads: usize,
+) -> Vec<Outlier> {
+    let mut outliers: Vec<Outlier> = map_on_threads(groups, threads, |(group, apples)| {
+        group_outliers(group, apples, options)
+    })
+    .into_iter()
+    .flatten()
+    .collect();
+
+    let attribute_position = |attribute: &str| {
+        AppleQuality::ATTRIBUTES
+            .iter()
+            .position(|a| *a == attribute)
+    };
+    outliers.sort_by_key(|outlier| {
+        (
+            outlier.id,
+            attribute_position(&outlier.attribute),
+            outlier.rule,
+        )
+    });
+    outliers
+}
+
+// writes one line per flagged value
+pub fn write_outliers_csv<P: AsRef<Path>>(
+    outliers: &[Outlier],
+    file_path: P,
+) -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_path(file_path)?;
+    for outlier in outliers {
+        wtr.serialize(outlier)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// the number of outliers for each (attribute, group, rule)
+fn outlier_counts(outliers: &[Outlier]) -> BTreeMap<(Option<usize>, String, OutlierRule), usize> {
+    let mut counts = BTreeMap::new();
+    for outlier in outliers {
+        let position = AppleQuality::ATTRIBUTES
+            .iter()
+            .position(|a| *a == outlier.attribute);
+        *counts
+            .entry((position, outlier.group.clone(), outlier.rule))
+            .or_insert(0) += 1;
+    }
+    counts
+}
+
+pub fn outlier_section(outliers: &[Outlier]) -> Section {
+    Section {
+        title: "Outliers".to_owned(),
+        header: ["Attribute", "Group", "Rule", "Count"]
+            .map(str::to_owned)
+            .to_vec(),
+        rows: outlier_counts(outliers)
+            .into_iter()
+            .map(|((position, group, rule), count)| {
+                vec![
+                    position
+                        .map_or("", |i| AppleQuality::ATTRIBUTES[i])
+                        .to_owned(),
+                    group,
+                    rule.name().to_owned(),
+                    count.to_string(),
+                ]
+            })
+            .collect(),
+    }
+}
+
+// one row per (attribute, group, rule), e.g. (Weight, good, outliers_iqr, 12)
+pub fn outlier_report_rows(outliers: &[Outlier]) -> Vec<ReportRow> {
+    outlier_counts(outliers)
+        .into_iter()
+        .map(|((position, group, rule), count)| ReportRow {
+            attribute: position
+                .map_or("", |i| AppleQuality::ATTRIBUTES[i])
+                .to_owned(),
+            group,
+            statistic: format!("outliers_{}", rule.name()),
+            value: count as f64,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // apples whose size takes the given values, the other attributes are 0
+    fn apples_with_sizes(sizes: &[f64]) -> Vec<AppleQuality> {
+        sizes
+            .iter()
+            .enumerate()
+            .map(|(id, &size)| {
+                let mut values = [0.0; 7];
+                values[0] = size;
+                AppleQuality::from_values(id as i32, values, "good")
+            })
+            .collect()
+    }
+
+    fn outliers_of(sizes: &[f64], options: OutlierOptions) -> Vec<Outlier> {
+        let apples = apples_with_sizes(sizes);
+        find_outliers(&[("good".to_owned(), apples.iter().collect())], options, 1)
+    }
+
+    #[test]
+    fn the_z_score_rule_flags_values_beyond_the_threshold_only() {
+        let sizes = [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 10.0];
+        // the mean is 1 and the standard deviation the square root of 10
+        let z = 9.0 / 10f64.sqrt();
+        let options = |z_threshold| OutlierOptions {
+            z_threshold,
+            iqr_multiplier: f64::INFINITY,
+        };
+
+        assert!(outliers_of(&sizes, options(z)).is_empty());
+        let outliers = outliers_of(&sizes, options(z - 1e-9));
+        assert_eq!(outliers.len(), 1);
+        assert_eq!(
+            (outliers[0].id, outliers[0].attribute.as_str()),
+            (9, "Size")
+        );
+        assert_eq!(outliers[0].rule, OutlierRule::ZScore);
+        assert!((outliers[0].score - z).abs() < 1e-12);
+    }
+
+    #[test]
+    fn the_iqr_rule_flags_values_beyond_the_fences() {
+        // the quartiles are 3 and 7, so the fences are at -3 and 13
+        let sizes = [-3.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 13.5];
+        let options = OutlierOptions {
+            z_threshold: f64::INFINITY,
+            iqr_multiplier: 1.5,
+        };
+
+        let outliers = outliers_of(&sizes, options);
+        assert_eq!(outliers.len(), 1);
+        assert_eq!((outliers[0].id, outliers[0].value), (8, 13.5));
+        assert_eq!(outliers[0].rule, OutlierRule::Iqr);
+        assert_eq!(outliers[0].score, 1.625);
+    }
+
+    #[test]
+    fn a_group_without_spread_has_no_outliers() {
+        let options = OutlierOptions::default();
+        assert!(outliers_of(&[2.0; 20], options).is_empty());
+        assert!(outliers_of(&[2.0], options).is_empty());
+
+        // most values equal leave no interquartile range, only the z-score flags
+        let mut sizes = [1.0; 20];
+        sizes[19] = 100.0;
+        let outliers = outliers_of(&sizes, options);
+        assert_eq!(outliers.len(), 1);
+        assert_eq!(outliers[0].rule, OutlierRule::ZScore);
+        assert!(outliers[0].score.is_finite());
+    }
+
+    #[test]
+    fn the_outliers_do_not_depend_on_the_number_of_threads() {
+        let mut sizes = [1.0; 20];
+        sizes[3] = 50.0;
+        let groups: Vec<Vec<AppleQuality>> = (0..5).map(|_| apples_with_sizes(&sizes)).collect();
+        let groups: Vec<(String, Vec<&AppleQuality>)> = groups
+            .iter()
+            .enumerate()
+            .map(|(i, apples)| (format!("group {}", i), apples.iter().collect()))
+            .collect();
+
+        let one = find_outliers(&groups, OutlierOptions::default(), 1);
+        assert_eq!(one.len(), 5);
+        assert_eq!(find_outliers(&groups, OutlierOptions::default(), 3), one);
+    }
+}
@@ -0,0 +1,284 @@
+use crossbeam::channel;
+use csv::ReaderBuilder;
+use std::collections::HashSet;
+use std::error::Error;
+use std::path::Path;
+use std::thread;
+
+use crate::aggregate::{AggregateState, GroupAggregates};
+use crate::apple_quality::AppleQuality;
+use crate::filter::Filter;
+use crate::group::GroupSummary;
+use crate::schema::{Record, RecordParser, Schema};
+use crate::validation::{
+    non_finite_value, open_csv, unreadable_row, RejectionReport, RowValidator, ValidationOptions,
+};
+
+#[derive(Clone, Debug)]
+pub struct PipelineOptions {
+    // number of summarizing worker threads
+    pub workers: usize,
+    // number of records sent over the channel in one message
+    pub batch_size: usize,
+    // number of batches that can wait in the channel before the reader blocks
+    pub channel_capacity: usize,
+    // when set, invalid rows are reported and skipped instead of failing the run
+    pub validation: Option<ValidationOptions>,
+    // when set, the workers only aggregate the records that match it
+    pub filter: Option<Filter>,
+}
+
+impl Default for PipelineOptions {
+    fn default() -> Self {
+        PipelineOptions {
+            workers: thread::available_parallelism().map_or(1, |workers| workers.get()),
+            batch_size: 1024,
+            channel_capacity: 16,
+            validation: None,
+            filter: None,
+        }
+    }
+}
+
+// - a reader thread streams batches of records over a bounded channel to a
+//   pool of workers, each worker keeps partial aggregates for every group
+// - the channel is bounded, so the reader blocks when the workers fall behind
+//   and no more than `channel_capacity` batches are ever held in memory
+// - when the file is exhausted the workers return their partial aggregates,
+//   which are merged into the final summaries
+// - count, mean, standard deviation, min and max match the in-memory summary,
+//   the median and the quantiles are estimated with a quantile sketch and the
+//   summaries are flagged with `quantiles_estimated`
+// - in lenient mode the reader validates each row and the rejected rows are
+//   returned in the report, in strict mode the first bad row fails the run
+pub fn summarize_csv_streaming<P: AsRef<Path>>(
+    file_path: P,
+    group_by: &str,
+    quantiles: &[f64],
+    options: &PipelineOptions,
+) -> Result<(Vec<GroupSummary>, RejectionReport), Box<dyn Error>> {
+    let (state, report) = aggregate_csv_streaming(file_path, group_by, options)?;
+    Ok((state.summaries(quantiles), report))
+}
+
+// the merged aggregates behind `summarize_csv_streaming`, which can be saved
+// and merged with the aggregates of a later file
+pub fn aggregate_csv_streaming<P: AsRef<Path>>(
+    file_path: P,
+    group_by: &str,
+    options: &PipelineOptions,
+) -> Result<(AggregateState, RejectionReport), Box<dyn Error>> {
+    let file = open_csv(file_path)?;
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(options.validation.is_some())
+        .from_reader(file);
+
+    // the header is checked before any thread starts
+    let mut validator = match &options.validation {
+        Some(validation) => Some(RowValidator::new(rdr.headers()?, validation.clone())?),
+        None => None,
+    };
+
+    let label_column = group_by.to_owned();
+    let read = move |emit: &mut dyn FnMut(Record) -> bool| -> Result<RejectionReport, BoxError> {
+        let headers = rdr.headers()?.clone();
+        let mut report = RejectionReport::default();
+
+        for record in rdr.records() {
+            let apple: AppleQuality = match &mut validator {
+                // in lenient mode a rejected row is recorded and skipped
+                Some(validator) => {
+                    let result = match record {
+                        Ok(record) => validator.validate(&record),
+                        Err(error) => Err(vec![unreadable_row(&error)]),
+                    };
+                    report.record(&result);
+                    match result {
+                        Ok(apple) => apple,
+                        Err(_) => continue,
+                    }
+                }
+                None => {
+                    report.rows_read += 1;
+                    let record = record?;
+                    let apple: AppleQuality = record.deserialize(Some(&headers))?;
+                    // rejected like the in-memory strict load does
+                    if let Some(error) = non_finite_value(&apple, &record) {
+                        return Err(error.to_string().into());
+                    }
+                    apple
+                }
+            };
+
+            if let Some(label) = apple.group_label(&label_column) {
+                let record = Record {
+                    group: label.to_owned(),
+                    values: apple.attribute_values().to_vec(),
+                };
+                if !emit(record) {
+                    break;
+                }
+            }
+        }
+        Ok(report)
+    };
+
+    aggregate_stream(read, group_by, &AppleQuality::ATTRIBUTES, options)
+}
+
+// - the same pipeline for any csv layout, the rows are read through a schema
+// - in lenient mode the rejected rows are returned in the report, in strict
+//   mode the first bad row fails the run
+pub fn summarize_schema_csv_streaming<P: AsRef<Path>>(
+    file_path: P,
+    schema: &Schema,
+    quantiles: &[f64],
+    options: &PipelineOptions,
+) -> Result<(Vec<GroupSummary>, RejectionReport), Box<dyn Error>> {
+    let (state, report) = aggregate_schema_csv_streaming(file_path, schema, options)?;
+    Ok((state.summaries(quantiles), report))
+}
+
+pub fn aggregate_schema_csv_streaming<P: AsRef<Path>>(
+    file_path: P,
+    schema: &Schema,
+    options: &PipelineOptions,
+) -> Result<(AggregateState, RejectionReport), Box<dyn Error>> {
+    let file = open_csv(file_path)?;
+    let lenient = options.validation.is_some();
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(lenient)
+        .from_reader(file);
+
+    // the header is checked before any thread starts
+    let mut parser = RecordParser::new(
+        schema,
+        rdr.headers()?,
+        options.validation.clone().unwrap_or_default(),
+    )?;
+
+    let read = move |emit: &mut dyn FnMut(Record) -> bool| -> Result<RejectionReport, BoxError> {
+        let mut report = RejectionReport::default();
+
+        for record in rdr.records() {
+            let result = match record {
+                Ok(record) => parser.parse(&record),
+                Err(error) if lenient => Err(vec![unreadable_row(&error)]),
+                Err(error) => return Err(error.into()),
+            };
+            report.record(&result);
+            let record = match result {
+                Ok(record) => record,
+                Err(mut errors) if !lenient => return Err(errors.remove(0).to_string().into()),
+                Err(_) => continue,
+            };
+
+            if !emit(record) {
+                break;
+            }
+        }
+        Ok(report)
+    };
+
+    aggregate_stream(read, &schema.group_column, &schema.numeric_columns, options)
+}
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+// - runs `read` on the reader thread, it hands every accepted record to `emit`
+//   and stops early when `emit` returns false because the workers are gone
+// - the records are batched, sent to the workers and the partial aggregates
+//   merged into one state
+fn aggregate_stream<R, S>(
+    read: R,
+    group_column: &str,
+    attributes: &[S],
+    options: &PipelineOptions,
+) -> Result<(AggregateState, RejectionReport), Box<dyn Error>>
+where
+    R: FnOnce(&mut dyn FnMut(Record) -> bool) -> Result<RejectionReport, BoxError> + Send + 'static,
+    S: AsRef<str>,
+{
+    let (tx, rx) = channel::bounded::<Vec<Record>>(options.channel_capacity.max(1));
+
+    let batch_size = options.batch_size.max(1);
+    let reader = thread::spawn(
+        move || -> Result<(Vec<String>, RejectionReport), BoxError> {
+            // the reader sees the records in file order, so it keeps track of
+            // the order the labels first appear in
+            let mut labels = Vec::new();
+            let mut seen = HashSet::new();
+
+            let mut batch = Vec::with_capacity(batch_size);
+            let report = read(&mut |record| {
+                if seen.insert(record.group.clone()) {
+                    labels.push(record.group.clone());
+                }
+
+                batch.push(record);
+                if batch.len() == batch_size {
+                    // send blocks while the channel is full
+                    let full_batch = std::mem::replace(&mut batch, Vec::with_capacity(batch_size));
+                    return tx.send(full_batch).is_ok();
+                }
+                true
+            })?;
+            if !batch.is_empty() {
+                let _ = tx.send(batch);
+            }
+
+            // dropping the sender closes the channel, which ends the workers' loops
+            Ok((labels, report))
+        },
+    );
+
+    let workers: Vec<_> = (0..options.workers.max(1))
+        .map(|_| {
+            // crossbeam receivers can be cloned, so every worker pulls batches
+            // from the same channel
+            let rx = rx.clone();
+            let filter = options.filter.clone();
+            thread::spawn(move || {
+                let mut aggregates = GroupAggregates::default();
+                for batch in rx {
+                    for record in &batch {
+                        if filter
+                            .as_ref()
+                            .is_none_or(|f| f.matches(&record.values, &record.group))
+                        {
+                            aggregates.push(&record.group, &record.values);
+                        }
+                    }
+                }
+                aggregates
+            })
+        })
+        .collect();
+    drop(rx);
+
+    let mut aggregates = GroupAggregates::default();
+    for worker in workers {
+        aggregates.merge(&worker.join().unwrap());
+    }
+    let (labels, report) = reader
+        .join()
+        .unwrap()
+        .map_err(|error| error as Box<dyn Error>)?;
+
+    let state = AggregateState {
+        group_column: group_column.to_owned(),
+        attributes: attributes
+            .iter()
+            .map(|attribute| attribute.as_ref().to_owned())
+            .collect(),
+        labels,
+        aggregates,
+        // the caller knows which file the records came from and the source
+        // of the filter
+        batches: Vec::new(),
+        filter: None,
+    };
+    Ok((state, report))
+}
@@ -0,0 +1,334 @@
+use csv::Writer;
+use prettytable::{format, Cell, Row, Table};
+use serde::Serialize;
+use std::error::Error;
+use std::io::Write;
+
+use crate::group::GroupSummary;
+use crate::significance::PairTest;
+
+// - one value of the comparative summary in long format
+// - the schema is the same for every output format and every dataset, so two
+//   reports can be diffed row by row
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ReportRow {
+    pub attribute: String,
+    pub group: String,
+    pub statistic: String,
+    pub value: f64,
+}
+
+// the rows ordered by attribute, then group, then statistic
+pub fn report_rows(summaries: &[GroupSummary]) -> Vec<ReportRow> {
+    let attribute_count = summaries
+        .first()
+        .map_or(0, |summary| summary.attributes.len());
+
+    let mut rows = Vec::new();
+    for i in 0..attribute_count {
+        for summary in summaries {
+            let attribute = &summary.attributes[i];
+            rows.extend(
+                attribute
+                    .statistics()
+                    .into_iter()
+                    .map(|(statistic, value)| ReportRow {
+                        attribute: attribute.attribute.clone(),
+                        group: summary.group.clone(),
+                        statistic,
+                        value,
+                    }),
+            );
+        }
+    }
+    rows
+}
+
+// - a titled table of already formatted cells
+// - every part of the report other than the comparative summary is rendered
+//   through a section, so it looks the same in the table and markdown outputs
+#[derive(Clone, Debug, PartialEq)]
+pub struct Section {
+    pub title: String,
+    pub header: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl Section {
+    pub fn display_in_table(&self) {
+        println!("{}", self.title);
+        print_table(&self.header, &self.rows);
+    }
+
+    pub fn write_markdown<W: Write>(&self, mut out: W) -> Result<(), Box<dyn Error>> {
+        writeln!(out, "### {}", self.title)?;
+        writeln!(out)?;
+        write_markdown_table(&self.header, &self.rows, out)
+    }
+}
+
+// - the comparative layout shared by the table and markdown outputs
+// - one row per attribute and statistic, one column per group
+// - each pair of groups that was tested adds a statistic, a p-value and an
+//   effect size column, filled on the row of the statistic the test compares
+fn comparative_rows(
+    summaries: &[GroupSummary],
+    tests: &[PairTest],
+) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut header = vec!["Attribute".to_owned(), "Statistic".to_owned()];
+    header.extend(summaries.iter().map(|summary| summary.group.clone()));
+
+    let mut pairs: Vec<String> = Vec::new();
+    for test in tests {
+        if !pairs.contains(&test.pair()) {
+            pairs.push(test.pair());
+        }
+    }
+    for pair in &pairs {
+        header.extend([
+            format!("t / U ({})", pair),
+            format!("p-value ({})", pair),
+            format!("effect size ({})", pair),
+        ]);
+    }
+
+    let attribute_count = summaries
+        .first()
+        .map_or(0, |summary| summary.attributes.len());
+
+    let mut rows = Vec::new();
+    for i in 0..attribute_count {
+        let statistics: Vec<Vec<(String, f64)>> = summaries
+            .iter()
+            .map(|summary| summary.attributes[i].statistics())
+            .collect();
+
+        for (j, (statistic, _)) in statistics[0].iter().enumerate() {
+            let mut row = vec![
+                summaries[0].attributes[i].attribute.clone(),
+                statistic.clone(),
+            ];
+            row.extend(
+                statistics
+                    .iter()
+                    .map(|group| format_statistic(statistic, group[j].1)),
+            );
+            for pair in &pairs {
+                let test = tests.iter().find(|test| {
+                    test.attribute == summaries[0].attributes[i].attribute
+                        && test.kind.statistic() == statistic
+                        && test.pair() == *pair
+                });
+                match test {
+                    Some(test) => row.extend(test.cells()),
+                    None => row.extend([String::new(), String::new(), String::new()]),
+                }
+            }
+            rows.push(row);
+        }
+    }
+
+    (header, rows)
+}
+
+fn print_table(header: &[String], rows: &[Vec<String>]) {
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+
+    // Adding the header row with attributes and one column per group for comparison
+    table.add_row(Row::new(
+        header.iter().map(|cell| Cell::new(cell)).collect(),
+    ));
+
+    // Appending a row for each attribute and statistic
+    for row in rows {
+        table.add_row(Row::new(row.iter().map(|cell| Cell::new(cell)).collect()));
+    }
+
+    // Printing the table
+    table.printstd();
+}
+
+fn write_markdown_table<W: Write>(
+    header: &[String],
+    rows: &[Vec<String>],
+    mut out: W,
+) -> Result<(), Box<dyn Error>> {
+    writeln!(out, "| {} |", markdown_cells(header))?;
+    writeln!(out, "|{}", " --- |".repeat(header.len()))?;
+    for row in rows {
+        writeln!(out, "| {} |", markdown_cells(row))?;
+    }
+    Ok(())
+}
+
+// - the cells of one markdown table row, escaped so a group label like "a|b"
+//   cannot add a column
+// - a line break would end the row, so it becomes a space
+fn markdown_cells(cells: &[String]) -> String {
+    cells
+        .iter()
+        .map(|cell| {
+            cell.replace('\\', "\\\\")
+                .replace('|', "\\|")
+                .replace(['\r', '\n'], " ")
+        })
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+pub fn display_comparative_summary_in_table(summaries: &[GroupSummary], tests: &[PairTest]) {
+    let (header, rows) = comparative_rows(summaries, tests);
+    print_table(&header, &rows);
+}
+
+pub fn write_markdown<W: Write>(
+    summaries: &[GroupSummary],
+    tests: &[PairTest],
+    out: W,
+) -> Result<(), Box<dyn Error>> {
+    let (header, rows) = comparative_rows(summaries, tests);
+    write_markdown_table(&header, &rows, out)
+}
+
+// NaN values, e.g. the standard deviation of a single apple, are written as null
+pub fn write_json<W: Write>(rows: &[ReportRow], mut out: W) -> Result<(), Box<dyn Error>> {
+    serde_json::to_writer_pretty(&mut out, rows)?;
+    writeln!(out)?;
+    Ok(())
+}
+
+pub fn write_csv<W: Write>(rows: &[ReportRow], out: W) -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_writer(out);
+    for row in rows {
+        wtr.serialize(row)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+// counts are whole numbers, everything else is shown with two decimals
+fn format_statistic(statistic: &str, value: f64) -> String {
+    if statistic == "count" {
+        format!("{}", value)
+    } else {
+        format!("{:.2}", value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::statistics::AttributeSummary;
+
+    fn summaries() -> Vec<GroupSummary> {
+        let summary = |group: &str, values: &mut [f64]| GroupSummary {
+            group: group.to_owned(),
+            attributes: vec![AttributeSummary::from_values("Size", values, &[0.25])],
+        };
+        vec![
+            summary("good", &mut [1.0, 3.0]),
+            summary("bad", &mut [-2.0]),
+        ]
+    }
+
+    #[test]
+    fn report_rows_are_long_format_by_attribute_group_and_statistic() {
+        let rows = report_rows(&summaries());
+        let keys: Vec<(&str, &str)> = rows
+            .iter()
+            .map(|row| (row.group.as_str(), row.statistic.as_str()))
+            .collect();
+        let statistics = ["count", "mean", "std_dev", "min", "p25", "median", "max"];
+        let expected: Vec<(&str, &str)> = ["good", "bad"]
+            .iter()
+            .flat_map(|group| statistics.iter().map(move |statistic| (*group, *statistic)))
+            .collect();
+        assert_eq!(keys, expected);
+        assert!(rows.iter().all(|row| row.attribute == "Size"));
+    }
+
+    #[test]
+    fn json_is_an_array_of_rows_with_nan_as_null() {
+        let rows = report_rows(&summaries());
+        let mut out = Vec::new();
+        write_json(&rows[..3], &mut out).unwrap();
+        let mut bad = Vec::new();
+        write_json(&rows[9..10], &mut bad).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            r#"[
+  {
+    "attribute": "Size",
+    "group": "good",
+    "statistic": "count",
+    "value": 2.0
+  },
+  {
+    "attribute": "Size",
+    "group": "good",
+    "statistic": "mean",
+    "value": 2.0
+  },
+  {
+    "attribute": "Size",
+    "group": "good",
+    "statistic": "std_dev",
+    "value": 1.4142135623730951
+  }
+]
+"#
+        );
+        // the standard deviation of a single value
+        let bad: serde_json::Value = serde_json::from_slice(&bad).unwrap();
+        assert_eq!(
+            bad,
+            serde_json::json!([{
+                "attribute": "Size",
+                "group": "bad",
+                "statistic": "std_dev",
+                "value": null
+            }])
+        );
+    }
+
+    #[test]
+    fn csv_has_a_header_and_one_line_per_row() {
+        let rows = report_rows(&summaries());
+        let mut out = Vec::new();
+        write_csv(&rows, &mut out).unwrap();
+        let csv = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+
+        assert_eq!(lines.len(), rows.len() + 1);
+        assert_eq!(
+            lines[..6],
+            [
+                "attribute,group,statistic,value",
+                "Size,good,count,2.0",
+                "Size,good,mean,2.0",
+                "Size,good,std_dev,1.4142135623730951",
+                "Size,good,min,1.0",
+                "Size,good,p25,1.5",
+            ]
+        );
+        assert_eq!(lines[10], "Size,bad,std_dev,NaN");
+    }
+
+    #[test]
+    fn markdown_cells_cannot_break_the_table() {
+        let section = Section {
+            title: "Groups".to_owned(),
+            header: vec!["Group".to_owned(), "Count".to_owned()],
+            rows: vec![vec!["a|b\\".to_owned(), "3".to_owned()]],
+        };
+        let mut out = Vec::new();
+        section.write_markdown(&mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "### Groups\n\n| Group | Count |\n| --- | --- |\n| a\\|b\\\\ | 3 |\n"
+        );
+    }
+}
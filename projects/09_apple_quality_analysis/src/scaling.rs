@@ -0,0 +1,276 @@
+use csv::Writer;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+
+use crate::apple_quality::AppleQuality;
+use crate::group::map_on_threads;
+use crate::report::Section;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScalingMethod {
+    // to the range 0 to 1 of the fitted rows
+    MinMax,
+    // to a mean of 0 and a standard deviation of 1
+    ZScore,
+}
+
+impl ScalingMethod {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScalingMethod::MinMax => "min-max",
+            ScalingMethod::ZScore => "z-score",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AttributeScaling {
+    pub attribute: String,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    // population standard deviation, like the classifier's standardizer
+    pub std_dev: f64,
+}
+
+impl AttributeScaling {
+    // - a constant attribute is only shifted, instead of divided by zero
+    pub fn scale(&self, method: ScalingMethod, value: f64) -> f64 {
+        let (offset, range) = match method {
+            ScalingMethod::MinMax => (self.min, self.max - self.min),
+            ScalingMethod::ZScore => (self.mean, self.std_dev),
+        };
+        if range > 0.0 {
+            (value - offset) / range
+        } else {
+            value - offset
+        }
+    }
+}
+
+// - the parameters of a scaling, fitted once and saved as json so later
+//   batches are scaled with exactly the same numbers
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ScalingParameters {
+    pub method: ScalingMethod,
+    // one entry per attribute, in the order of AppleQuality::ATTRIBUTES
+    pub attributes: Vec<AttributeScaling>,
+}
+
+impl ScalingParameters {
+    // - fits the attributes on at most `threads` scoped threads, each one
+    //   reading a column of the shared apples
+    // - no rows is an error, their min and max would be infinite and could
+    //   not be saved
+    pub fn fit(
+        apples: &[AppleQuality],
+        method: ScalingMethod,
+        threads: usize,
+    ) -> Result<ScalingParameters, Box<dyn Error>> {
+        if apples.is_empty() {
+            return Err("cannot fit the scaling parameters without any rows".into());
+        }
+
+        let columns: Vec<usize> = (0..AppleQuality::ATTRIBUTES.len()).collect();
+        let attributes = map_on_threads(&columns, threads, |&i| {
+            let values: Vec<f64> = apples
+                .iter()
+                .map(|apple| apple.attribute_values()[i])
+                .collect();
+            fit_attribute(AppleQuality::ATTRIBUTES[i], &values)
+        });
+
+        Ok(ScalingParameters { method, attributes })
+    }
+
+    pub fn load<P: AsRef<Path>>(file_path: P) -> Result<ScalingParameters, Box<dyn Error>> {
+        let parameters: ScalingParameters = serde_json::from_reader(File::open(file_path)?)?;
+
+        // parameters fitted on another layout would silently scale the wrong columns
+        let attributes: Vec<&str> = parameters
+            .attributes
+            .iter()
+            .map(|scaling| scaling.attribute.as_str())
+            .collect();
+        if attributes != AppleQuality::ATTRIBUTES {
+            return Err(format!(
+                "the scaling parameters are for {:?}, expected {:?}",
+                attributes,
+                AppleQuality::ATTRIBUTES
+            )
+            .into());
+        }
+        Ok(parameters)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, file_path: P) -> Result<(), Box<dyn Error>> {
+        let file = BufWriter::new(File::create(file_path)?);
+        serde_json::to_writer_pretty(file, self)?;
+        Ok(())
+    }
+
+    pub fn transform(&self, apple: &AppleQuality) -> Vec<f64> {
+        self.attributes
+            .iter()
+            .zip(apple.attribute_values())
+            .map(|(scaling, value)| scaling.scale(self.method, value))
+            .collect()
+    }
+
+    pub fn section(&self) -> Section {
+        Section {
+            title: format!("Scaling parameters ({})", self.method.name()),
+            header: ["Attribute", "min", "max", "mean", "std_dev"]
+                .map(str::to_owned)
+                .to_vec(),
+            rows: self
+                .attributes
+                .iter()
+                .map(|scaling| {
+                    vec![
+                        scaling.attribute.clone(),
+                        format!("{:.4}", scaling.min),
+                        format!("{:.4}", scaling.max),
+                        format!("{:.4}", scaling.mean),
+                        format!("{:.4}", scaling.std_dev),
+                    ]
+                })
+                .collect(),
+        }
+    }
+}
+
+fn fit_attribute(attribute: &str, values: &[f64]) -> AttributeScaling {
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values
+        .iter()
+        .map(|value| (value - mean).powi(2))
+        .sum::<f64>()
+        / n;
+
+    AttributeScaling {
+        attribute: attribute.to_owned(),
+        min: values.iter().copied().fold(f64::INFINITY, f64::min),
+        max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+        mean,
+        std_dev: variance.sqrt(),
+    }
+}
+
+// writes the scaled apples with their original A_id and Quality
+pub fn write_scaled_csv<P: AsRef<Path>>(
+    apples: &[AppleQuality],
+    parameters: &ScalingParameters,
+    file_path: P,
+) -> Result<(), Box<dyn Error>> {
+    let mut wtr = Writer::from_path(file_path)?;
+
+    let mut header = vec!["A_id"];
+    header.extend(AppleQuality::ATTRIBUTES);
+    header.push("Quality");
+    wtr.write_record(&header)?;
+
+    for apple in apples {
+        let mut record = vec![apple.id.to_string()];
+        record.extend(
+            parameters
+                .transform(apple)
+                .iter()
+                .map(|value| value.to_string()),
+        );
+        record.push(apple.quality.clone());
+        wtr.write_record(&record)?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn apples() -> Vec<AppleQuality> {
+        [[2.0, 10.0], [4.0, 10.0], [6.0, 10.0], [8.0, 10.0]]
+            .iter()
+            .enumerate()
+            .map(|(id, &[size, weight])| {
+                let values = [size, weight, -size, 0.0, 0.0, 0.0, 0.0];
+                AppleQuality::from_values(id as i32, values, "good")
+            })
+            .collect()
+    }
+
+    #[test]
+    fn min_max_scaling_maps_the_fitted_range_to_0_and_1() {
+        let apples = apples();
+        let parameters = ScalingParameters::fit(&apples, ScalingMethod::MinMax, 1).unwrap();
+        assert_eq!(
+            (parameters.attributes[0].min, parameters.attributes[0].max),
+            (2.0, 8.0)
+        );
+
+        let sizes: Vec<f64> = apples
+            .iter()
+            .map(|apple| parameters.transform(apple)[0])
+            .collect();
+        assert_eq!(sizes, [0.0, 1.0 / 3.0, 2.0 / 3.0, 1.0]);
+        // a constant attribute is shifted to 0 instead of divided by zero
+        assert_eq!(parameters.transform(&apples[0])[1], 0.0);
+        assert_eq!(parameters.transform(&apples[0])[2], 1.0);
+    }
+
+    #[test]
+    fn z_score_scaling_centres_on_the_mean_in_population_deviations() {
+        let apples = apples();
+        let parameters = ScalingParameters::fit(&apples, ScalingMethod::ZScore, 3).unwrap();
+        let size = &parameters.attributes[0];
+        assert_eq!((size.mean, size.std_dev), (5.0, 5f64.sqrt()));
+
+        let sizes: Vec<f64> = apples
+            .iter()
+            .map(|apple| parameters.transform(apple)[0])
+            .collect();
+        assert_eq!(sizes[0], -3.0 / 5f64.sqrt());
+        assert_eq!(sizes.iter().sum::<f64>(), 0.0);
+        assert_eq!(parameters.transform(&apples[3])[1], 0.0);
+    }
+
+    #[test]
+    fn no_rows_cannot_be_fitted() {
+        let error = ScalingParameters::fit(&[], ScalingMethod::MinMax, 1).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "cannot fit the scaling parameters without any rows"
+        );
+    }
+
+    #[test]
+    fn saved_parameters_load_back_and_other_layouts_are_rejected() {
+        let parameters = ScalingParameters::fit(&apples(), ScalingMethod::ZScore, 2).unwrap();
+        let file_path = std::env::temp_dir().join(format!(
+            "apple_scaling_parameters_{}.json",
+            std::process::id()
+        ));
+
+        parameters.save(&file_path).unwrap();
+        let loaded = ScalingParameters::load(&file_path);
+
+        let mut renamed = parameters.clone();
+        renamed.attributes[1].attribute = "Colour".to_owned();
+        renamed.save(&file_path).unwrap();
+        let rejected = ScalingParameters::load(&file_path);
+        fs::remove_file(&file_path).unwrap();
+
+        assert_eq!(loaded.unwrap(), parameters);
+        assert!(rejected
+            .unwrap_err()
+            .to_string()
+            .starts_with("the scaling parameters are for [\"Size\", \"Colour\","));
+    }
+}
@@ -0,0 +1,324 @@
+use csv::{ReaderBuilder, StringRecord};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::error::Error;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::filter::Filter;
+use crate::group::{summarize_labels, GroupSummary};
+use crate::statistics::AttributeSummary;
+use crate::validation::{
+    line_number, open_csv, unreadable_row, Rejection, RejectionReport, RowError, ValidationOptions,
+};
+
+// number of rows read to decide which columns are numeric
+const SAMPLE_ROWS: usize = 100;
+
+// - the layout of a csv file: which column splits the rows into groups and
+//   which columns are summarized
+// - discovered from the file itself, or loaded from a json config such as
+//   {"group_column": "Grade", "numeric_columns": ["Diameter", "Brix"], "id_column": "P_id"}
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Schema {
+    pub group_column: String,
+    pub numeric_columns: Vec<String>,
+    // when set, a repeated id rejects the row
+    #[serde(default)]
+    pub id_column: Option<String>,
+}
+
+impl Schema {
+    // - a column is numeric when every non-empty value of the first rows is a number
+    // - columns named id or ending in _id are ids, not attributes
+    // - the rows are grouped by `group_by` when given, otherwise by the first
+    //   column that is neither numeric nor an id
+    pub fn discover<P: AsRef<Path>>(
+        file_path: P,
+        group_by: Option<&str>,
+    ) -> Result<Schema, Box<dyn Error>> {
+        let file = File::open(file_path)?;
+        let mut rdr = ReaderBuilder::new()
+            .has_headers(true)
+            .flexible(true)
+            .from_reader(file);
+        let headers: Vec<String> = rdr
+            .headers()?
+            .iter()
+            .map(|header| header.trim().to_owned())
+            .collect();
+
+        // a column stays numeric until a sampled value fails to parse
+        let mut numeric = vec![true; headers.len()];
+        let mut seen_value = vec![false; headers.len()];
+        for record in rdr.records().take(SAMPLE_ROWS) {
+            let record = record?;
+            for (i, value) in record.iter().map(str::trim).enumerate().take(headers.len()) {
+                if value.is_empty() {
+                    continue;
+                }
+                seen_value[i] = true;
+                numeric[i] &= value.parse::<f64>().is_ok();
+            }
+        }
+
+        let is_id = |header: &str| {
+            let header = header.to_ascii_lowercase();
+            header == "id" || header.ends_with("_id")
+        };
+        let id_column = headers.iter().find(|header| is_id(header)).cloned();
+
+        let group_column = match group_by {
+            Some(group_by) => headers
+                .iter()
+                .find(|header| header.eq_ignore_ascii_case(group_by))
+                .ok_or_else(|| format!("the csv header has no {} column", group_by))?,
+            None => headers
+                .iter()
+                .enumerate()
+                .find(|(i, header)| !numeric[*i] && !is_id(header))
+                .map(|(_, header)| header)
+                .ok_or("the csv header has no text column to group by")?,
+        }
+        .clone();
+
+        let numeric_columns: Vec<String> = headers
+            .iter()
+            .enumerate()
+            .filter(|(i, header)| {
+                numeric[*i] && seen_value[*i] && !is_id(header) && **header != group_column
+            })
+            .map(|(_, header)| header.clone())
+            .collect();
+        if numeric_columns.is_empty() {
+            return Err("the csv file has no numeric columns to summarize".into());
+        }
+
+        Ok(Schema {
+            group_column,
+            numeric_columns,
+            id_column,
+        })
+    }
+
+    // reads a schema from a json config, `group_by` replaces its group column
+    pub fn load<P: AsRef<Path>>(
+        file_path: P,
+        group_by: Option<&str>,
+    ) -> Result<Schema, Box<dyn Error>> {
+        let mut schema: Schema = serde_json::from_reader(File::open(file_path)?)?;
+        if let Some(group_by) = group_by {
+            schema.group_column = group_by.to_owned();
+        }
+        Ok(schema)
+    }
+}
+
+// one row read through a schema: its group label and its numeric values,
+// in the order of the schema's numeric columns
+#[derive(Clone, Debug, PartialEq)]
+pub struct Record {
+    pub group: String,
+    pub values: Vec<f64>,
+}
+
+// - the schema-driven counterpart of RowValidator, checks each csv row on its
+//   own and builds a Record from it
+// - remembers the ids it has accepted to catch duplicates
+pub struct RecordParser {
+    schema: Schema,
+    // the position of the id, the group and the numeric columns in the header
+    id_column: Option<usize>,
+    group_column: usize,
+    numeric_columns: Vec<usize>,
+    options: ValidationOptions,
+    seen_ids: HashSet<String>,
+}
+
+impl RecordParser {
+    pub fn new(
+        schema: &Schema,
+        headers: &StringRecord,
+        options: ValidationOptions,
+    ) -> Result<Self, Box<dyn Error>> {
+        let position = |name: &str| {
+            headers
+                .iter()
+                .position(|header| header.trim().eq_ignore_ascii_case(name))
+                .ok_or_else(|| format!("the csv header has no {} column", name))
+        };
+
+        Ok(RecordParser {
+            schema: schema.clone(),
+            id_column: schema.id_column.as_deref().map(position).transpose()?,
+            group_column: position(&schema.group_column)?,
+            numeric_columns: schema
+                .numeric_columns
+                .iter()
+                .map(|column| position(column))
+                .collect::<Result<_, _>>()?,
+            options,
+            seen_ids: HashSet::new(),
+        })
+    }
+
+    pub fn parse(&mut self, record: &StringRecord) -> Result<Record, Vec<RowError>> {
+        let line = line_number(record.position());
+        let mut errors = Vec::new();
+        let mut reject = |column: &str, rejection: Rejection| {
+            errors.push(RowError {
+                line,
+                column: Some(column.to_owned()),
+                rejection,
+            });
+        };
+
+        let field = |index: usize| record.get(index).map(str::trim).unwrap_or("");
+
+        let id = match (self.id_column, &self.schema.id_column) {
+            (Some(index), Some(column)) => match field(index) {
+                "" => {
+                    reject(column, Rejection::Missing);
+                    None
+                }
+                id if self.seen_ids.contains(id) => {
+                    reject(column, Rejection::DuplicateId(id.to_owned()));
+                    None
+                }
+                id => Some(id.to_owned()),
+            },
+            _ => None,
+        };
+
+        let mut values = Vec::with_capacity(self.numeric_columns.len());
+        for (&index, column) in self
+            .numeric_columns
+            .iter()
+            .zip(&self.schema.numeric_columns)
+        {
+            match field(index) {
+                "" => reject(column, Rejection::Missing),
+                text => match text.parse::<f64>() {
+                    Ok(parsed) if parsed.is_finite() => values.push(parsed),
+                    Ok(_) => reject(column, Rejection::NotFinite(text.to_owned())),
+                    Err(_) => reject(column, Rejection::NotANumber(text.to_owned())),
+                },
+            }
+        }
+
+        let group = field(self.group_column);
+        if group.is_empty() {
+            reject(&self.schema.group_column, Rejection::Missing);
+        } else if let Some(allowed_labels) = &self.options.allowed_labels {
+            if !allowed_labels.contains(group) {
+                reject(
+                    &self.schema.group_column,
+                    Rejection::UnknownLabel(group.to_owned()),
+                );
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // only ids of accepted rows count towards duplicates
+        if let Some(id) = id {
+            self.seen_ids.insert(id);
+        }
+
+        Ok(Record {
+            group: group.to_owned(),
+            values,
+        })
+    }
+}
+
+// - loads every row of the file through the schema
+// - with validation options the invalid rows are reported and skipped, without
+//   them the first invalid row fails the load
+pub fn read_records_csv<P: AsRef<Path>>(
+    file_path: P,
+    schema: &Schema,
+    validation: Option<ValidationOptions>,
+) -> Result<(Vec<Record>, RejectionReport), Box<dyn Error>> {
+    let file = open_csv(file_path)?;
+    let lenient = validation.is_some();
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(lenient)
+        .from_reader(file);
+    let mut parser = RecordParser::new(schema, rdr.headers()?, validation.unwrap_or_default())?;
+
+    let mut records = Vec::new();
+    let mut report = RejectionReport::default();
+    for record in rdr.records() {
+        let result = match record {
+            Ok(record) => parser.parse(&record),
+            Err(error) if lenient => Err(vec![unreadable_row(&error)]),
+            Err(error) => return Err(error.into()),
+        };
+        report.record(&result);
+        match result {
+            Ok(record) => records.push(record),
+            Err(mut errors) if !lenient => return Err(errors.remove(0).to_string().into()),
+            Err(_) => {}
+        }
+    }
+
+    Ok((records, report))
+}
+
+// the distinct group labels, in the order they first appear
+pub fn distinct_record_labels(records: &[Record]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    records
+        .iter()
+        .filter(|record| seen.insert(record.group.as_str()))
+        .map(|record| record.group.clone())
+        .collect()
+}
+
+// summarizes the records of each group on the same bounded pool of worker
+// threads as `summarize_groups`, skipping the records that do not match the filter
+pub fn summarize_records(
+    records: Arc<Vec<Record>>,
+    schema: &Schema,
+    quantiles: &[f64],
+    threads: usize,
+    filter: Option<&Filter>,
+) -> Vec<GroupSummary> {
+    let labels = distinct_record_labels(&records);
+    let attributes = schema.numeric_columns.clone();
+    let quantiles = quantiles.to_vec();
+    let filter = filter.cloned();
+
+    summarize_labels(records, labels, threads, move |records, label| {
+        // one column of values for each numeric column of the schema
+        let mut columns = vec![Vec::new(); attributes.len()];
+        for record in records.iter().filter(|record| {
+            record.group == label
+                && filter
+                    .as_ref()
+                    .is_none_or(|f| f.matches(&record.values, &record.group))
+        }) {
+            for (column, &value) in columns.iter_mut().zip(&record.values) {
+                column.push(value);
+            }
+        }
+        if columns.first().is_none_or(Vec::is_empty) {
+            return None;
+        }
+
+        Some(
+            attributes
+                .iter()
+                .zip(columns.iter_mut())
+                .map(|(attribute, values)| {
+                    AttributeSummary::from_values(attribute, values, &quantiles)
+                })
+                .collect(),
+        )
+    })
+}
@@ -0,0 +1,441 @@
+use std::f64::consts::PI;
+
+use crate::apple_quality::AppleQuality;
+use crate::group::{map_on_threads, GroupSummary};
+use crate::report::ReportRow;
+use crate::statistics::AttributeSummary;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TestKind {
+    // compares the means, without assuming equal variances
+    Welch,
+    // compares the ranks, without assuming normal distributions
+    MannWhitney,
+}
+
+impl TestKind {
+    // the row of the comparative summary the test is shown on
+    pub fn statistic(&self) -> &'static str {
+        match self {
+            TestKind::Welch => "mean",
+            TestKind::MannWhitney => "median",
+        }
+    }
+
+    // the names of the test statistic, the p-value and the effect size
+    pub fn names(&self) -> [&'static str; 3] {
+        match self {
+            TestKind::Welch => ["welch_t", "welch_p", "cohens_d"],
+            TestKind::MannWhitney => ["mann_whitney_u", "mann_whitney_p", "rank_biserial"],
+        }
+    }
+}
+
+// - the result of one test between two groups for one attribute
+// - a positive effect size means the values of `group_a` tend to be larger
+#[derive(Clone, Debug, PartialEq)]
+pub struct PairTest {
+    pub attribute: String,
+    pub group_a: String,
+    pub group_b: String,
+    pub kind: TestKind,
+    pub statistic: f64,
+    // two-sided
+    pub p_value: f64,
+    pub effect_size: f64,
+}
+
+impl PairTest {
+    pub fn pair(&self) -> String {
+        format!("{} vs {}", self.group_a, self.group_b)
+    }
+
+    // the statistic, the p-value and the effect size as table cells
+    pub fn cells(&self) -> [String; 3] {
+        let p_value = if self.p_value < 0.001 {
+            format!("{:.2e}", self.p_value)
+        } else {
+            format!("{:.3}", self.p_value)
+        };
+        [
+            format!("{:.2}", self.statistic),
+            p_value,
+            format!("{:.2}", self.effect_size),
+        ]
+    }
+
+    pub fn report_rows(&self) -> Vec<ReportRow> {
+        self.kind
+            .names()
+            .into_iter()
+            .zip([self.statistic, self.p_value, self.effect_size])
+            .map(|(statistic, value)| ReportRow {
+                attribute: self.attribute.clone(),
+                group: self.pair(),
+                statistic: statistic.to_owned(),
+                value,
+            })
+            .collect()
+    }
+}
+
+// - Welch's t-test for every attribute between every pair of groups
+// - only needs the count, mean and standard deviation, so it works on the
+//   summaries of every mode, streaming included
+pub fn welch_tests(summaries: &[GroupSummary]) -> Vec<PairTest> {
+    let mut tests = Vec::new();
+    for (i, a) in summaries.iter().enumerate() {
+        for b in &summaries[i + 1..] {
+            for (x, y) in a.attributes.iter().zip(&b.attributes) {
+                let (t, p_value) = welch_t_test(x, y);
+                tests.push(PairTest {
+                    attribute: x.attribute.clone(),
+                    group_a: a.group.clone(),
+                    group_b: b.group.clone(),
+                    kind: TestKind::Welch,
+                    statistic: t,
+                    p_value,
+                    effect_size: cohens_d(x, y),
+                });
+            }
+        }
+    }
+    tests
+}
+
+// - the Mann-Whitney U test for every attribute between every pair of groups
+// - it ranks the values themselves, so the attributes are tested on at most
+//   `threads` scoped threads over the apples already in memory
+pub fn mann_whitney_tests(
+    groups: &[(String, Vec<&AppleQuality>)],
+    threads: usize,
+) -> Vec<PairTest> {
+    let attributes: Vec<usize> = (0..AppleQuality::ATTRIBUTES.len()).collect();
+    let per_attribute: Vec<Vec<PairTest>> = map_on_threads(&attributes, threads, |&attribute| {
+        let columns: Vec<Vec<f64>> = groups
+            .iter()
+            .map(|(_, apples)| {
+                apples
+                    .iter()
+                    .map(|apple| apple.attribute_values()[attribute])
+                    .collect()
+            })
+            .collect();
+
+        let mut tests = Vec::new();
+        for i in 0..groups.len() {
+            for j in i + 1..groups.len() {
+                let (u, p_value, r) = mann_whitney_u(&columns[i], &columns[j]);
+                tests.push(PairTest {
+                    attribute: AppleQuality::ATTRIBUTES[attribute].to_string(),
+                    group_a: groups[i].0.clone(),
+                    group_b: groups[j].0.clone(),
+                    kind: TestKind::MannWhitney,
+                    statistic: u,
+                    p_value,
+                    effect_size: r,
+                });
+            }
+        }
+        tests
+    });
+
+    // the same pair order as the Welch tests: pair first, then attribute
+    let mut tests: Vec<PairTest> = per_attribute.into_iter().flatten().collect();
+    let pairs: Vec<String> = tests.iter().map(PairTest::pair).collect();
+    tests.sort_by_key(|test| pairs.iter().position(|pair| *pair == test.pair()));
+    tests
+}
+
+// returns the t statistic and its two-sided p-value
+fn welch_t_test(a: &AttributeSummary, b: &AttributeSummary) -> (f64, f64) {
+    let (n_a, n_b) = (a.count as f64, b.count as f64);
+    let (v_a, v_b) = (a.std_dev.powi(2) / n_a, b.std_dev.powi(2) / n_b);
+
+    let t = (a.mean - b.mean) / (v_a + v_b).sqrt();
+    // Welch–Satterthwaite degrees of freedom
+    let df = (v_a + v_b).powi(2) / (v_a.powi(2) / (n_a - 1.0) + v_b.powi(2) / (n_b - 1.0));
+
+    (t, student_t_two_sided(t, df))
+}
+
+// difference of the means in pooled standard deviations
+fn cohens_d(a: &AttributeSummary, b: &AttributeSummary) -> f64 {
+    let (n_a, n_b) = (a.count as f64, b.count as f64);
+    let pooled = (((n_a - 1.0) * a.std_dev.powi(2) + (n_b - 1.0) * b.std_dev.powi(2))
+        / (n_a + n_b - 2.0))
+        .sqrt();
+    (a.mean - b.mean) / pooled
+}
+
+// - returns U of the first sample, its two-sided p-value and the rank-biserial
+//   correlation
+// - the p-value uses the normal approximation with a correction for ties,
+//   which is accurate for groups of more than a few dozen values
+fn mann_whitney_u(a: &[f64], b: &[f64]) -> (f64, f64, f64) {
+    let (n_a, n_b) = (a.len() as f64, b.len() as f64);
+    let n = n_a + n_b;
+
+    let mut values: Vec<(f64, bool)> = a
+        .iter()
+        .map(|&value| (value, true))
+        .chain(b.iter().map(|&value| (value, false)))
+        .collect();
+    values.sort_by(|x, y| x.0.total_cmp(&y.0));
+
+    // tied values share the average of their ranks
+    let mut rank_sum_a = 0.0;
+    let mut tie_correction = 0.0;
+    let mut i = 0;
+    while i < values.len() {
+        let mut j = i;
+        while j + 1 < values.len() && values[j + 1].0 == values[i].0 {
+            j += 1;
+        }
+        let ties = (j - i + 1) as f64;
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        rank_sum_a += rank * values[i..=j].iter().filter(|(_, in_a)| *in_a).count() as f64;
+        tie_correction += ties.powi(3) - ties;
+        i = j + 1;
+    }
+
+    let u = rank_sum_a - n_a * (n_a + 1.0) / 2.0;
+    let mean = n_a * n_b / 2.0;
+    let sigma = (n_a * n_b / 12.0 * ((n + 1.0) - tie_correction / (n * (n - 1.0)))).sqrt();
+
+    // continuity correction towards the mean
+    let z = ((u - mean).abs() - 0.5).max(0.0) / sigma;
+    let p_value = erfc(z / 2f64.sqrt());
+
+    (u, p_value, 2.0 * u / (n_a * n_b) - 1.0)
+}
+
+// two-sided p-value of Student's t distribution, through the regularized
+// incomplete beta function
+fn student_t_two_sided(t: f64, df: f64) -> f64 {
+    if !t.is_finite() || !df.is_finite() {
+        return f64::NAN;
+    }
+    incomplete_beta(df / 2.0, 0.5, df / (df + t * t))
+}
+
+// the regularized incomplete beta function I_x(a, b), evaluated with a
+// continued fraction (Numerical Recipes, betai and betacf)
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+
+    let front =
+        (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+
+    // the continued fraction converges quickly on this side of the mean
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_continued_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_continued_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+fn beta_continued_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY: f64 = 1e-300;
+    const EPSILON: f64 = 1e-14;
+
+    let mut c = 1.0;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+
+    for m in 1..=300 {
+        let m = m as f64;
+
+        // the even and the odd step of the modified Lentz method
+        for numerator in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0)),
+        ] {
+            d = 1.0 + numerator * d;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = 1.0 + numerator / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            h *= d * c;
+        }
+
+        if (d * c - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+    h
+}
+
+// the logarithm of the gamma function, Lanczos approximation with g = 7
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.999_999_999_999_809_9,
+        676.520_368_121_885_1,
+        -1_259.139_216_722_402_8,
+        771.323_428_777_653_1,
+        -176.615_029_162_140_6,
+        12.507_343_278_686_905,
+        -0.138_571_095_265_720_12,
+        9.984_369_578_019_572e-6,
+        1.505_632_735_149_311_6e-7,
+    ];
+
+    if x < 0.5 {
+        // reflection formula
+        return (PI / (PI * x).sin()).ln() - ln_gamma(1.0 - x);
+    }
+
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, coefficient) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += coefficient / (x + i as f64);
+    }
+    let t = x + 7.5;
+    0.5 * (2.0 * PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+// the complementary error function, with a relative error below 1.2e-7
+// (Numerical Recipes, erfcc)
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let polynomial = -z * z - 1.265_512_23
+        + t * (1.000_023_68
+            + t * (0.374_091_96
+                + t * (0.096_784_18
+                    + t * (-0.186_288_06
+                        + t * (0.278_868_07
+                            + t * (-1.135_203_98
+                                + t * (1.488_515_87 + t * (-0.822_152_23 + t * 0.170_872_77))))))));
+    let result = t * polynomial.exp();
+    if x >= 0.0 {
+        result
+    } else {
+        2.0 - result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: f64, expected: f64, tolerance: f64) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{} is not within {} of {}",
+            actual,
+            tolerance,
+            expected
+        );
+    }
+
+    fn summary(count: usize, mean: f64, std_dev: f64) -> AttributeSummary {
+        AttributeSummary {
+            attribute: "Size".to_owned(),
+            count,
+            mean,
+            std_dev,
+            min: f64::NAN,
+            max: f64::NAN,
+            median: f64::NAN,
+            quantiles: Vec::new(),
+            quantiles_estimated: false,
+        }
+    }
+
+    #[test]
+    fn special_functions_match_known_values() {
+        // ln 4! and ln sqrt(pi)
+        assert_close(ln_gamma(5.0), 24f64.ln(), 1e-12);
+        assert_close(ln_gamma(0.5), PI.sqrt().ln(), 1e-12);
+        // I_x(a, 1) = x^a, and I_0.5(a, a) = 0.5 by symmetry
+        assert_close(incomplete_beta(2.5, 1.0, 0.3), 0.3f64.powf(2.5), 1e-12);
+        assert_close(incomplete_beta(4.0, 4.0, 0.5), 0.5, 1e-12);
+        assert_close(erfc(0.0), 1.0, 1e-7);
+        assert_close(erfc(1.0), 0.157_299_207_050_285_13, 1e-7);
+        assert_close(erfc(-1.0), 1.842_700_792_949_715, 1e-7);
+    }
+
+    #[test]
+    fn student_t_p_values_match_the_tables() {
+        // the two-sided 5% critical values for 1 and 10 degrees of freedom
+        assert_close(student_t_two_sided(12.706_204_736, 1.0), 0.05, 1e-9);
+        assert_close(student_t_two_sided(-2.228_138_852, 10.0), 0.05, 1e-9);
+        // with 1 degree of freedom the distribution is Cauchy
+        assert_close(student_t_two_sided(1.0, 1.0), 0.5, 1e-12);
+        assert_close(student_t_two_sided(0.0, 7.0), 1.0, 1e-12);
+        assert!(student_t_two_sided(f64::NAN, 7.0).is_nan());
+    }
+
+    #[test]
+    fn welch_t_test_of_two_equal_variance_groups() {
+        // equal sizes and variances give n_a + n_b - 2 degrees of freedom
+        let (a, b) = (summary(6, 1.0, 1.0), summary(6, 0.0, 1.0));
+        let (t, p_value) = welch_t_test(&a, &b);
+
+        assert_close(t, 3f64.sqrt(), 1e-12);
+        // integrated numerically from the t density with 10 degrees of freedom
+        assert_close(p_value, 0.113_937_412_151_9, 1e-9);
+        assert_close(cohens_d(&a, &b), 1.0, 1e-12);
+    }
+
+    #[test]
+    fn mann_whitney_u_of_two_separated_groups() {
+        let (u, p_value, effect) = mann_whitney_u(&[1.0, 2.0, 3.0], &[4.0, 5.0, 6.0]);
+
+        assert_eq!(u, 0.0);
+        assert_eq!(effect, -1.0);
+        // z = (4.5 - 0.5) / sqrt(5.25), through the normal approximation
+        assert_close(p_value, 0.080_855_598_370_052_3, 1e-7);
+
+        // ties share their ranks, so identical groups are not different at all
+        let (u, p_value, effect) = mann_whitney_u(&[1.0, 1.0, 2.0], &[1.0, 1.0, 2.0]);
+        assert_eq!(u, 4.5);
+        assert_eq!(effect, 0.0);
+        assert_close(p_value, 1.0, 1e-7);
+    }
+
+    #[test]
+    fn mann_whitney_tests_do_not_depend_on_the_number_of_threads() {
+        let groups: Vec<Vec<AppleQuality>> = (0..3)
+            .map(|group| {
+                (0..8)
+                    .map(|i| {
+                        let x = (i * (group + 2)) as f64;
+                        AppleQuality::from_values(
+                            i,
+                            [x, -x, x % 3.0, x % 5.0, x, x * x, x.sqrt()],
+                            "g",
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        let groups: Vec<(String, Vec<&AppleQuality>)> = groups
+            .iter()
+            .enumerate()
+            .map(|(i, apples)| (format!("g{}", i), apples.iter().collect()))
+            .collect();
+
+        let one = mann_whitney_tests(&groups, 1);
+        // every attribute of each of the three pairs, pair by pair
+        assert_eq!(one.len(), 3 * AppleQuality::ATTRIBUTES.len());
+        assert_eq!(one[0].pair(), one[6].pair());
+        assert_eq!(one[6].attribute, "Acidity");
+        assert_ne!(one[6].pair(), one[7].pair());
+        assert_eq!(mann_whitney_tests(&groups, 4), one);
+    }
+}
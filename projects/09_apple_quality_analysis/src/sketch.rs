@@ -0,0 +1,200 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+
+// how many centroids the sketch keeps, larger values are more accurate
+pub const DEFAULT_COMPRESSION: f64 = 200.0;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+struct Centroid {
+    mean: f64,
+    weight: f64,
+}
+
+// - a mergeable t-digest used to estimate quantiles in constant memory
+// - values are buffered and periodically compressed into a bounded number of
+//   centroids, which are small near the tails and larger around the median
+// - two sketches built from different parts of a file can be merged into one
+//   that estimates the quantiles of the whole file
+// - it serializes as its centroids and buffer, so it can be saved and merged
+//   with the sketch of a later batch
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct QuantileSketch {
+    compression: f64,
+    centroids: Vec<Centroid>,
+    buffer: Vec<f64>,
+    min: f64,
+    max: f64,
+}
+
+impl Default for QuantileSketch {
+    fn default() -> Self {
+        QuantileSketch::new(DEFAULT_COMPRESSION)
+    }
+}
+
+impl QuantileSketch {
+    pub fn new(compression: f64) -> QuantileSketch {
+        QuantileSketch {
+            compression,
+            centroids: Vec::new(),
+            buffer: Vec::new(),
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+
+    pub fn push(&mut self, value: f64) {
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+        self.buffer.push(value);
+
+        if self.buffer.len() >= (self.compression * 5.0) as usize {
+            self.compress();
+        }
+    }
+
+    pub fn merge(&mut self, other: &QuantileSketch) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.centroids.extend_from_slice(&other.centroids);
+        self.buffer.extend_from_slice(&other.buffer);
+        self.compress();
+    }
+
+    pub fn count(&self) -> f64 {
+        self.centroids.iter().map(|c| c.weight).sum::<f64>() + self.buffer.len() as f64
+    }
+
+    // estimates the value at quantile q, between 0 and 1
+    pub fn quantile(&self, q: f64) -> f64 {
+        let mut sketch = self.clone();
+        sketch.compress();
+        let centroids = &sketch.centroids;
+
+        if centroids.is_empty() {
+            return f64::NAN;
+        }
+        if centroids.len() == 1 {
+            return centroids[0].mean;
+        }
+
+        let total: f64 = centroids.iter().map(|c| c.weight).sum();
+        let rank = q * total;
+
+        // each centroid sits at the middle of the ranks it covers, the
+        // estimate is interpolated between the two centroids around the rank
+        let mut cumulative = 0.0;
+        let mut previous = (0.0, self.min);
+        for centroid in centroids {
+            let center = cumulative + centroid.weight / 2.0;
+            if rank < center {
+                let fraction = (rank - previous.0) / (center - previous.0);
+                return previous.1 + (centroid.mean - previous.1) * fraction;
+            }
+            previous = (center, centroid.mean);
+            cumulative += centroid.weight;
+        }
+
+        let fraction = (rank - previous.0) / (total - previous.0);
+        previous.1 + (self.max - previous.1) * fraction.min(1.0)
+    }
+
+    // merges the buffered values and the existing centroids into a new,
+    // bounded set of centroids
+    fn compress(&mut self) {
+        if self.buffer.is_empty() && self.centroids.len() <= self.compression as usize {
+            return;
+        }
+
+        let mut centroids = std::mem::take(&mut self.centroids);
+        centroids.extend(self.buffer.drain(..).map(|value| Centroid {
+            mean: value,
+            weight: 1.0,
+        }));
+        centroids.sort_by(|a, b| a.mean.total_cmp(&b.mean));
+
+        let total: f64 = centroids.iter().map(|c| c.weight).sum();
+        let mut merged: Vec<Centroid> = Vec::new();
+        let mut weight_so_far = 0.0;
+        let mut k_lower = self.scale(0.0);
+
+        for centroid in centroids {
+            match merged.last_mut() {
+                Some(current)
+                    if self.scale((weight_so_far + current.weight + centroid.weight) / total)
+                        - k_lower
+                        <= 1.0 =>
+                {
+                    let weight = current.weight + centroid.weight;
+                    current.mean += (centroid.mean - current.mean) * centroid.weight / weight;
+                    current.weight = weight;
+                }
+                Some(current) => {
+                    weight_so_far += current.weight;
+                    k_lower = self.scale(weight_so_far / total);
+                    merged.push(centroid);
+                }
+                None => merged.push(centroid),
+            }
+        }
+
+        self.centroids = merged;
+    }
+
+    // the k1 scale function of the t-digest paper
+    fn scale(&self, q: f64) -> f64 {
+        self.compression / (2.0 * PI) * (2.0 * q.clamp(0.0, 1.0) - 1.0).asin()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a fixed, shuffled looking sequence of the values 0 to n - 1
+    fn values(n: usize) -> Vec<f64> {
+        (0..n).map(|i| ((i * 7919) % n) as f64).collect()
+    }
+
+    #[test]
+    fn quantiles_of_a_small_sketch_are_exact_at_the_ends() {
+        let mut sketch = QuantileSketch::default();
+        for value in [3.0, 1.0, 2.0] {
+            sketch.push(value);
+        }
+        assert_eq!(sketch.count(), 3.0);
+        assert_eq!(sketch.quantile(0.0), 1.0);
+        assert_eq!(sketch.quantile(1.0), 3.0);
+        assert_eq!(sketch.quantile(0.5), 2.0);
+    }
+
+    #[test]
+    fn an_empty_sketch_has_no_quantiles() {
+        assert!(QuantileSketch::default().quantile(0.5).is_nan());
+    }
+
+    #[test]
+    fn merging_keeps_every_value_and_estimates_the_whole() {
+        let all = values(10_000);
+        let mut whole = QuantileSketch::default();
+        let mut parts = vec![QuantileSketch::default(); 4];
+        for (i, &value) in all.iter().enumerate() {
+            whole.push(value);
+            parts[i % 4].push(value);
+        }
+
+        let mut merged = QuantileSketch::default();
+        for part in &parts {
+            merged.merge(part);
+        }
+
+        // the weights add up exactly, whatever the merge order
+        assert_eq!(merged.count(), 10_000.0);
+        for q in [0.01, 0.05, 0.25, 0.5, 0.75, 0.95, 0.99] {
+            let exact = q * 9_999.0;
+            // within 0.5% of the range of the values
+            assert!((merged.quantile(q) - exact).abs() < 50.0, "q = {}", q);
+            assert!((whole.quantile(q) - exact).abs() < 50.0, "q = {}", q);
+        }
+    }
+}
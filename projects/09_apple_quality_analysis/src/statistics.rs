@@ -0,0 +1,176 @@
+// the quantiles reported next to the median when none are configured
+pub const DEFAULT_QUANTILES: [f64; 4] = [0.05, 0.25, 0.75, 0.95];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct AttributeSummary {
+    pub attribute: String,
+    pub count: usize,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub median: f64,
+    // pairs of (quantile, value), e.g. (0.25, -1.02) for the 25th percentile
+    pub quantiles: Vec<(f64, f64)>,
+    // - true when the median and the quantiles are estimated from a quantile
+    //   sketch instead of read from the sorted values
+    // - their statistics are then labelled "(est.)" in every report format
+    pub quantiles_estimated: bool,
+}
+
+impl AttributeSummary {
+    // - the values are sorted in place, so the median and the quantiles can be
+    //   read directly from their positions
+    // - an empty slice produces a summary with a count of zero and NaN values
+    pub fn from_values(attribute: &str, values: &mut [f64], quantiles: &[f64]) -> AttributeSummary {
+        values.sort_by(f64::total_cmp);
+
+        let count = values.len();
+        let mean = values.iter().sum::<f64>() / count as f64;
+
+        // sample standard deviation, dividing by n - 1
+        let std_dev = if count > 1 {
+            let squared_deviations: f64 = values.iter().map(|value| (value - mean).powi(2)).sum();
+            (squared_deviations / (count - 1) as f64).sqrt()
+        } else {
+            f64::NAN
+        };
+
+        AttributeSummary {
+            attribute: attribute.to_owned(),
+            count,
+            mean,
+            std_dev,
+            min: values.first().copied().unwrap_or(f64::NAN),
+            max: values.last().copied().unwrap_or(f64::NAN),
+            median: quantile(values, 0.5),
+            quantiles: quantiles
+                .iter()
+                .map(|&q| (q, quantile(values, q)))
+                .collect(),
+            quantiles_estimated: false,
+        }
+    }
+
+    // the statistics in the order they are displayed, paired with their labels
+    pub fn statistics(&self) -> Vec<(String, f64)> {
+        let mut statistics = vec![
+            ("count".to_owned(), self.count as f64),
+            ("mean".to_owned(), self.mean),
+            ("std_dev".to_owned(), self.std_dev),
+            ("min".to_owned(), self.min),
+        ];
+        statistics.extend(
+            self.quantiles
+                .iter()
+                .filter(|(q, _)| *q < 0.5)
+                .map(|&(q, value)| (self.estimate_label(quantile_label(q)), value)),
+        );
+        statistics.push((self.estimate_label("median".to_owned()), self.median));
+        statistics.extend(
+            self.quantiles
+                .iter()
+                .filter(|(q, _)| *q >= 0.5)
+                .map(|&(q, value)| (self.estimate_label(quantile_label(q)), value)),
+        );
+        statistics.push(("max".to_owned(), self.max));
+        statistics
+    }
+
+    fn estimate_label(&self, label: String) -> String {
+        if self.quantiles_estimated {
+            format!("{} (est.)", label)
+        } else {
+            label
+        }
+    }
+}
+
+// - linear interpolation between the two closest ranks
+// - the values must already be sorted in ascending order
+// - panics when q is not between 0 and 1, the command line checks the
+//   quantiles it is given before they get here
+pub fn quantile(sorted_values: &[f64], q: f64) -> f64 {
+    assert!(
+        (0.0..=1.0).contains(&q),
+        "the quantile {} is not between 0 and 1",
+        q
+    );
+    if sorted_values.is_empty() {
+        return f64::NAN;
+    }
+
+    let position = q * (sorted_values.len() - 1) as f64;
+    let lower = position.floor() as usize;
+    let upper = position.ceil() as usize;
+    let fraction = position - lower as f64;
+
+    sorted_values[lower] + (sorted_values[upper] - sorted_values[lower]) * fraction
+}
+
+// formats 0.05 as "p5" and 0.975 as "p97.5"
+pub fn quantile_label(q: f64) -> String {
+    format!("p{}", (q * 1000.0).round() / 10.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quantiles_interpolate_between_the_closest_ranks() {
+        let values = [1.0, 2.0, 4.0, 8.0, 16.0];
+        assert_eq!(quantile(&values, 0.0), 1.0);
+        assert_eq!(quantile(&values, 1.0), 16.0);
+        assert_eq!(quantile(&values, 0.5), 4.0);
+        // rank 0.3 * 4 = 1.2, a fifth of the way from 2 to 4
+        assert_eq!(quantile(&values, 0.3), 2.4);
+        assert_eq!(quantile(&values, 0.9), 12.8);
+    }
+
+    #[test]
+    fn a_single_value_is_every_quantile() {
+        for q in [0.0, 0.25, 0.5, 1.0] {
+            assert_eq!(quantile(&[3.5], q), 3.5);
+        }
+        assert!(quantile(&[], 0.5).is_nan());
+    }
+
+    #[test]
+    #[should_panic(expected = "the quantile 1.5 is not between 0 and 1")]
+    fn a_quantile_above_1_is_rejected() {
+        quantile(&[1.0, 2.0], 1.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "the quantile -0.1 is not between 0 and 1")]
+    fn a_quantile_below_0_is_rejected() {
+        quantile(&[1.0, 2.0], -0.1);
+    }
+
+    #[test]
+    fn quantile_labels_are_percentiles_with_one_decimal() {
+        assert_eq!(quantile_label(0.05), "p5");
+        assert_eq!(quantile_label(0.25), "p25");
+        assert_eq!(quantile_label(0.975), "p97.5");
+        assert_eq!(quantile_label(0.999), "p99.9");
+        assert_eq!(quantile_label(0.0), "p0");
+    }
+
+    #[test]
+    fn the_summary_lists_its_statistics_in_display_order() {
+        let mut values = [4.0, 1.0, 3.0, 2.0];
+        let summary = AttributeSummary::from_values("Size", &mut values, &[0.75, 0.25]);
+        let labels: Vec<String> = summary
+            .statistics()
+            .into_iter()
+            .map(|(label, _)| label)
+            .collect();
+        assert_eq!(
+            labels,
+            ["count", "mean", "std_dev", "min", "p25", "median", "p75", "max"]
+        );
+        assert_eq!(summary.median, 2.5);
+        assert_eq!(summary.quantiles, [(0.75, 3.25), (0.25, 1.75)]);
+    }
+}
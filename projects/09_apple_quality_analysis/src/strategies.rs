@@ -0,0 +1,215 @@
+use crossbeam::thread as crossbeam_thread;
+use rayon::prelude::*;
+use std::sync::Arc;
+use std::thread;
+
+use crate::aggregate::GroupAggregates;
+use crate::apple_quality::AppleQuality;
+use crate::group::{distinct_labels, GroupSummary};
+
+// the rows rayon aggregates in one task, small enough that idle threads can
+// steal the remaining chunks from busy ones
+const WORK_STEALING_CHUNK_ROWS: usize = 4096;
+
+// the ways the apples can be aggregated, compared by the benchmarks
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Strategy {
+    // a single pass on the calling thread
+    Sequential,
+    // two spawned threads sharing the apples through an arc, each one
+    // aggregating half of the rows, like the original good/bad threads
+    TwoThreadArc,
+    // the rows are split into one chunk per thread, each scoped thread
+    // borrows and aggregates its chunk
+    ScopedChunks,
+    // rayon balances small chunks of rows over its work-stealing pool
+    WorkStealing,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 4] = [
+        Strategy::Sequential,
+        Strategy::TwoThreadArc,
+        Strategy::ScopedChunks,
+        Strategy::WorkStealing,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Strategy::Sequential => "sequential",
+            Strategy::TwoThreadArc => "two-thread arc",
+            Strategy::ScopedChunks => "crossbeam scoped chunks",
+            Strategy::WorkStealing => "rayon work-stealing",
+        }
+    }
+}
+
+// - summarizes every group, splitting the rows into chunks the way the
+//   strategy does, aggregating each chunk into partial aggregates for every
+//   group and merging the partial aggregates at the end
+// - the rows are the unit of work, so every strategy can keep all its
+//   threads busy whatever the number of groups
+// - count, min and max are exact and the mean and the standard deviation
+//   agree across strategies up to rounding, the median and the quantiles are
+//   estimated from the merged sketches and flagged with `quantiles_estimated`
+pub fn summarize_with(
+    strategy: Strategy,
+    apples: &Arc<Vec<AppleQuality>>,
+    group_by: &str,
+    quantiles: &[f64],
+    threads: usize,
+) -> Vec<GroupSummary> {
+    let aggregates = match strategy {
+        Strategy::Sequential => aggregate_chunk(apples, group_by),
+        Strategy::TwoThreadArc => aggregate_two_thread_arc(apples, group_by),
+        Strategy::ScopedChunks => aggregate_scoped_chunks(apples, group_by, threads),
+        Strategy::WorkStealing => aggregate_work_stealing(apples, group_by),
+    };
+
+    aggregates.summaries(
+        &AppleQuality::ATTRIBUTES,
+        &distinct_labels(apples, group_by),
+        quantiles,
+    )
+}
+
+fn aggregate_chunk(apples: &[AppleQuality], group_by: &str) -> GroupAggregates {
+    let mut aggregates = GroupAggregates::default();
+    for apple in apples {
+        if let Some(label) = apple.group_label(group_by) {
+            aggregates.push(label, &apple.attribute_values());
+        }
+    }
+    aggregates
+}
+
+fn aggregate_two_thread_arc(apples: &Arc<Vec<AppleQuality>>, group_by: &str) -> GroupAggregates {
+    let half = apples.len().div_ceil(2);
+
+    let handles: Vec<_> = [0..half, half..apples.len()]
+        .into_iter()
+        .map(|rows| {
+            let apples = Arc::clone(apples);
+            let group_by = group_by.to_owned();
+            thread::spawn(move || aggregate_chunk(&apples[rows], &group_by))
+        })
+        .collect();
+
+    let mut aggregates = GroupAggregates::default();
+    for handle in handles {
+        aggregates.merge(&handle.join().unwrap());
+    }
+    aggregates
+}
+
+fn aggregate_scoped_chunks(
+    apples: &[AppleQuality],
+    group_by: &str,
+    threads: usize,
+) -> GroupAggregates {
+    let chunk_size = apples.len().div_ceil(threads.max(1)).max(1);
+
+    // the scoped threads borrow their chunk of the rows, nothing is cloned
+    crossbeam_thread::scope(|s| {
+        let handles: Vec<_> = apples
+            .chunks(chunk_size)
+            .map(|chunk| s.spawn(move |_| aggregate_chunk(chunk, group_by)))
+            .collect();
+
+        let mut aggregates = GroupAggregates::default();
+        for handle in handles {
+            aggregates.merge(&handle.join().unwrap());
+        }
+        aggregates
+    })
+    .unwrap()
+}
+
+fn aggregate_work_stealing(apples: &[AppleQuality], group_by: &str) -> GroupAggregates {
+    apples
+        .par_chunks(WORK_STEALING_CHUNK_ROWS)
+        .map(|chunk| aggregate_chunk(chunk, group_by))
+        .reduce(GroupAggregates::default, |mut aggregates, other| {
+            aggregates.merge(&other);
+            aggregates
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::apple_quality::{read_apple_quality_csv, summarize_apples};
+    use crate::group::group_apples;
+
+    #[test]
+    fn every_strategy_agrees_with_the_in_memory_summary() {
+        let file_path = concat!(env!("CARGO_MANIFEST_DIR"), "/../data/apple_quality.csv");
+        let apples = Arc::new(read_apple_quality_csv(file_path).unwrap());
+        let quantiles = [0.25, 0.75];
+
+        let exact: Vec<_> = group_apples(&apples, "Quality", None)
+            .into_iter()
+            .map(|(label, group)| (label, summarize_apples(&group, &quantiles)))
+            .collect();
+        assert_eq!(exact.len(), 2);
+
+        for strategy in Strategy::ALL {
+            let summaries = summarize_with(strategy, &apples, "Quality", &quantiles, 3);
+            assert_eq!(summaries.len(), exact.len(), "{}", strategy.name());
+
+            for (summary, (label, attributes)) in summaries.iter().zip(&exact) {
+                assert_eq!(&summary.group, label, "{}", strategy.name());
+                for (estimated, exact) in summary.attributes.iter().zip(attributes) {
+                    let context = format!("{} {} {}", strategy.name(), label, exact.attribute);
+                    assert_eq!(estimated.count, exact.count, "{}", context);
+                    assert_eq!(estimated.min, exact.min, "{}", context);
+                    assert_eq!(estimated.max, exact.max, "{}", context);
+                    assert!((estimated.mean - exact.mean).abs() < 1e-12, "{}", context);
+                    assert!(
+                        (estimated.std_dev - exact.std_dev).abs() < 1e-12,
+                        "{}",
+                        context
+                    );
+                    // the sketch estimates are close, not exact
+                    assert!(estimated.quantiles_estimated, "{}", context);
+                    assert!(
+                        (estimated.median - exact.median).abs() < 0.02,
+                        "{}",
+                        context
+                    );
+                    for ((_, estimated), (_, exact)) in
+                        estimated.quantiles.iter().zip(&exact.quantiles)
+                    {
+                        assert!((estimated - exact).abs() < 0.02, "{}", context);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn the_chunks_of_a_single_group_merge_back_to_the_whole() {
+        let apples: Vec<AppleQuality> = (0..10_000)
+            .map(|id| AppleQuality {
+                id,
+                size: id as f64,
+                weight: 0.0,
+                sweetness: 0.0,
+                crunchiness: 0.0,
+                juiciness: 0.0,
+                ripeness: 0.0,
+                acidity: 0.0,
+                quality: "good".to_owned(),
+            })
+            .collect();
+        let apples = Arc::new(apples);
+
+        for strategy in Strategy::ALL {
+            let summaries = summarize_with(strategy, &apples, "Quality", &[], 4);
+            let size = &summaries[0].attributes[0];
+            assert_eq!(size.count, 10_000, "{}", strategy.name());
+            assert_eq!((size.min, size.max), (0.0, 9_999.0), "{}", strategy.name());
+            assert!((size.mean - 4_999.5).abs() < 1e-9, "{}", strategy.name());
+        }
+    }
+}
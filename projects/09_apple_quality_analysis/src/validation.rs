@@ -0,0 +1,498 @@
+use csv::{ReaderBuilder, StringRecord, Writer};
+use prettytable::{format, Cell, Row, Table};
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::apple_quality::AppleQuality;
+
+#[derive(Clone, Debug, Default)]
+pub struct ValidationOptions {
+    // when set, a quality label outside of this set rejects the row
+    pub allowed_labels: Option<HashSet<String>>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Rejection {
+    Missing,
+    NotANumber(String),
+    NotFinite(String),
+    UnknownLabel(String),
+    DuplicateId(String),
+    Malformed(String),
+}
+
+impl Rejection {
+    // a short name for the kind of rejection, used to count them
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Rejection::Missing => "missing",
+            Rejection::NotANumber(_) => "not a number",
+            Rejection::NotFinite(_) => "not finite",
+            Rejection::UnknownLabel(_) => "unknown label",
+            Rejection::DuplicateId(_) => "duplicate id",
+            Rejection::Malformed(_) => "malformed",
+        }
+    }
+}
+
+impl fmt::Display for Rejection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rejection::Missing => write!(f, "missing value"),
+            Rejection::NotANumber(value) => write!(f, "{:?} is not a number", value),
+            Rejection::NotFinite(value) => write!(f, "{:?} is not a finite number", value),
+            Rejection::UnknownLabel(value) => write!(f, "{:?} is not an allowed label", value),
+            Rejection::DuplicateId(id) => write!(f, "id {} was already used", id),
+            Rejection::Malformed(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct RowError {
+    // the line number in the csv file, the header is line 1
+    pub line: u64,
+    // the column the problem was found in, none when the whole row is unreadable
+    pub column: Option<String>,
+    pub rejection: Rejection,
+}
+
+impl fmt::Display for RowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.column {
+            Some(column) => write!(f, "line {}, {}: {}", self.line, column, self.rejection),
+            None => write!(f, "line {}: {}", self.line, self.rejection),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct RejectionReport {
+    pub rows_read: usize,
+    pub rows_rejected: usize,
+    // every problem found, a rejected row can have more than one
+    pub errors: Vec<RowError>,
+}
+
+impl RejectionReport {
+    pub fn record<T>(&mut self, result: &Result<T, Vec<RowError>>) {
+        self.rows_read += 1;
+        if let Err(errors) = result {
+            self.rows_rejected += 1;
+            self.errors.extend_from_slice(errors);
+        }
+    }
+
+    // the number of problems for each (column, kind) pair
+    pub fn counts(&self) -> BTreeMap<(String, &'static str), usize> {
+        let mut counts = BTreeMap::new();
+        for error in &self.errors {
+            let column = error.column.clone().unwrap_or_else(|| "(row)".to_owned());
+            *counts.entry((column, error.rejection.kind())).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    // writes one line per problem, so the rejected rows can be fixed at the source
+    pub fn write_csv<P: AsRef<Path>>(&self, file_path: P) -> Result<(), Box<dyn Error>> {
+        let mut wtr = Writer::from_path(file_path)?;
+        wtr.write_record(["line", "column", "kind", "reason"])?;
+        for error in &self.errors {
+            wtr.write_record([
+                error.line.to_string(),
+                error.column.clone().unwrap_or_default(),
+                error.rejection.kind().to_owned(),
+                error.rejection.to_string(),
+            ])?;
+        }
+        wtr.flush()?;
+        Ok(())
+    }
+}
+
+pub fn display_rejection_summary_in_table(report: &RejectionReport) {
+    println!(
+        "Rejected {} of {} rows",
+        report.rows_rejected, report.rows_read
+    );
+    if report.errors.is_empty() {
+        return;
+    }
+
+    let mut table = Table::new();
+    table.set_format(*format::consts::FORMAT_NO_LINESEP_WITH_TITLE);
+    table.set_titles(Row::new(vec![
+        Cell::new("Column"),
+        Cell::new("Reason"),
+        Cell::new("Count"),
+    ]));
+    for ((column, kind), count) in report.counts() {
+        table.add_row(Row::new(vec![
+            Cell::new(&column),
+            Cell::new(kind),
+            Cell::new(&count.to_string()),
+        ]));
+    }
+    table.printstd();
+}
+
+// - checks each csv row on its own and builds an AppleQuality from it, so a bad
+//   row can be reported and skipped instead of failing the whole load
+// - remembers the ids it has accepted to catch duplicates
+pub struct RowValidator {
+    // the position of A_id, the attributes and Quality in the header
+    id_column: usize,
+    attribute_columns: Vec<usize>,
+    quality_column: usize,
+    options: ValidationOptions,
+    seen_ids: HashSet<i32>,
+}
+
+impl RowValidator {
+    pub fn new(headers: &StringRecord, options: ValidationOptions) -> Result<Self, Box<dyn Error>> {
+        let position = |name: &str| {
+            headers
+                .iter()
+                .position(|header| header.trim() == name)
+                .ok_or_else(|| format!("the csv header has no {} column", name))
+        };
+
+        Ok(RowValidator {
+            id_column: position("A_id")?,
+            attribute_columns: AppleQuality::ATTRIBUTES
+                .iter()
+                .map(|attribute| position(attribute))
+                .collect::<Result<_, _>>()?,
+            quality_column: position("Quality")?,
+            options,
+            seen_ids: HashSet::new(),
+        })
+    }
+
+    pub fn validate(&mut self, record: &StringRecord) -> Result<AppleQuality, Vec<RowError>> {
+        let line = line_number(record.position());
+        let mut errors = Vec::new();
+        let mut reject = |column: &str, rejection: Rejection| {
+            errors.push(RowError {
+                line,
+                column: Some(column.to_owned()),
+                rejection,
+            });
+        };
+
+        let field = |index: usize| record.get(index).map(str::trim).unwrap_or("");
+
+        let id = match field(self.id_column) {
+            "" => {
+                reject("A_id", Rejection::Missing);
+                None
+            }
+            value => match value.parse::<i32>() {
+                Ok(id) if self.seen_ids.contains(&id) => {
+                    reject("A_id", Rejection::DuplicateId(value.to_owned()));
+                    None
+                }
+                Ok(id) => Some(id),
+                Err(_) => {
+                    reject("A_id", Rejection::NotANumber(value.to_owned()));
+                    None
+                }
+            },
+        };
+
+        let mut values = [0.0; 7];
+        for ((value, &index), attribute) in values
+            .iter_mut()
+            .zip(&self.attribute_columns)
+            .zip(AppleQuality::ATTRIBUTES)
+        {
+            match field(index) {
+                "" => reject(attribute, Rejection::Missing),
+                text => match text.parse::<f64>() {
+                    Ok(parsed) if parsed.is_finite() => *value = parsed,
+                    Ok(_) => reject(attribute, Rejection::NotFinite(text.to_owned())),
+                    Err(_) => reject(attribute, Rejection::NotANumber(text.to_owned())),
+                },
+            }
+        }
+
+        let quality = field(self.quality_column);
+        if quality.is_empty() {
+            reject("Quality", Rejection::Missing);
+        } else if let Some(allowed_labels) = &self.options.allowed_labels {
+            if !allowed_labels.contains(quality) {
+                reject("Quality", Rejection::UnknownLabel(quality.to_owned()));
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // only ids of accepted rows count towards duplicates
+        let id = id.unwrap();
+        self.seen_ids.insert(id);
+
+        let [size, weight, sweetness, crunchiness, juiciness, ripeness, acidity] = values;
+        Ok(AppleQuality {
+            id,
+            size,
+            weight,
+            sweetness,
+            crunchiness,
+            juiciness,
+            ripeness,
+            acidity,
+            quality: quality.to_owned(),
+        })
+    }
+}
+
+// - the line a record starts on, the header is line 1
+// - the reader's line count includes the lines of quoted fields that span
+//   several lines, it is exact for files opened with `open_csv`
+pub(crate) fn line_number(position: Option<&csv::Position>) -> u64 {
+    position.map_or(0, csv::Position::line)
+}
+
+// - opens a csv file with its line endings turned into "\n", for readers that
+//   report line numbers
+// - the csv reader takes a record's position after the "\r" of a windows line
+//   ending and before its "\n", so its line count would be one short
+pub(crate) fn open_csv<P: AsRef<Path>>(file_path: P) -> io::Result<UnixLineEndings<File>> {
+    Ok(UnixLineEndings::new(File::open(file_path)?))
+}
+
+// - a reader that turns "\r\n" and a lone "\r" into "\n", also inside quoted
+//   fields
+// - a "\r" at the end of one read is remembered, so a "\n" at the start of
+//   the next is dropped
+pub(crate) struct UnixLineEndings<R> {
+    inner: R,
+    after_carriage_return: bool,
+}
+
+impl<R: Read> UnixLineEndings<R> {
+    pub(crate) fn new(inner: R) -> UnixLineEndings<R> {
+        UnixLineEndings {
+            inner,
+            after_carriage_return: false,
+        }
+    }
+}
+
+impl<R: Read> Read for UnixLineEndings<R> {
+    fn read(&mut self, buffer: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let read = self.inner.read(buffer)?;
+            if read == 0 {
+                return Ok(0);
+            }
+
+            // the bytes are compacted in place, a dropped "\n" only ever
+            // moves the later bytes forward
+            let mut kept = 0;
+            for i in 0..read {
+                let byte = buffer[i];
+                if byte == b'\n' && self.after_carriage_return {
+                    self.after_carriage_return = false;
+                    continue;
+                }
+                self.after_carriage_return = byte == b'\r';
+                buffer[kept] = if byte == b'\r' { b'\n' } else { byte };
+                kept += 1;
+            }
+            // a read of nothing but a dropped "\n" is not the end of the file
+            if kept > 0 {
+                return Ok(kept);
+            }
+        }
+    }
+}
+
+// - the first attribute of a row that is NaN or infinite, as the validator
+//   would report it
+// - strict loading fails on it, NaN and infinity would spread through the
+//   statistics and could not be saved in a state file
+pub(crate) fn non_finite_value(apple: &AppleQuality, record: &StringRecord) -> Option<RowError> {
+    AppleQuality::ATTRIBUTES
+        .iter()
+        .zip(apple.attribute_values())
+        .find(|(_, value)| !value.is_finite())
+        .map(|(attribute, value)| RowError {
+            line: line_number(record.position()),
+            column: Some(attribute.to_string()),
+            rejection: Rejection::NotFinite(value.to_string()),
+        })
+}
+
+// a row that the csv reader itself cannot read, e.g. because of invalid utf-8
+pub fn unreadable_row(error: &csv::Error) -> RowError {
+    RowError {
+        line: line_number(error.position()),
+        column: None,
+        rejection: Rejection::Malformed(error.to_string()),
+    }
+}
+
+// - loads the valid rows and reports the invalid ones instead of failing
+// - only a missing file or a header without the expected columns is an error
+pub fn read_apple_quality_csv_lenient<P: AsRef<Path>>(
+    file_path: P,
+    options: ValidationOptions,
+) -> Result<(Vec<AppleQuality>, RejectionReport), Box<dyn Error>> {
+    let file = open_csv(file_path)?;
+
+    // flexible lets rows with too few or too many fields through, so they can
+    // be reported as missing values instead of stopping the reader
+    let mut rdr = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(file);
+    let mut validator = RowValidator::new(rdr.headers()?, options)?;
+
+    let mut apples = Vec::new();
+    let mut report = RejectionReport::default();
+    for record in rdr.records() {
+        let result = match record {
+            Ok(record) => validator.validate(&record),
+            Err(error) => Err(vec![unreadable_row(&error)]),
+        };
+        report.record(&result);
+        if let Ok(apple) = result {
+            apples.push(apple);
+        }
+    }
+
+    Ok((apples, report))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str =
+        "A_id,Size,Weight,Sweetness,Crunchiness,Juiciness,Ripeness,Acidity,Quality";
+
+    // validates every row of `csv` and returns the problems found
+    fn validate_csv(csv: &str) -> Vec<RowError> {
+        let mut rdr = ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(UnixLineEndings::new(csv.as_bytes()));
+        let mut validator = RowValidator::new(
+            &rdr.headers().unwrap().clone(),
+            ValidationOptions::default(),
+        )
+        .unwrap();
+        let mut errors = Vec::new();
+        for record in rdr.records() {
+            if let Err(mut row_errors) = validator.validate(&record.unwrap()) {
+                errors.append(&mut row_errors);
+            }
+        }
+        errors
+    }
+
+    #[test]
+    fn rejections_name_the_line_counting_the_header_as_line_1() {
+        let csv = format!(
+            "{}\n0,1,1,1,1,1,1,1,good\n1,1,x,1,1,1,1,1,good\n0,1,1,1,1,1,1,1,bad\n",
+            HEADER
+        );
+        let errors = validate_csv(&csv);
+
+        assert_eq!(
+            errors,
+            vec![
+                RowError {
+                    line: 3,
+                    column: Some("Weight".to_owned()),
+                    rejection: Rejection::NotANumber("x".to_owned()),
+                },
+                RowError {
+                    line: 4,
+                    column: Some("A_id".to_owned()),
+                    rejection: Rejection::DuplicateId("0".to_owned()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn windows_line_endings_give_the_same_lines() {
+        let csv = format!(
+            "{}\r\n0,1,1,1,1,1,1,1,good\r\n1,1,1,,1,1,1,1,good\r\n",
+            HEADER
+        );
+        let errors = validate_csv(&csv);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 3);
+        assert_eq!(errors[0].rejection, Rejection::Missing);
+    }
+
+    #[test]
+    fn a_quoted_field_over_several_lines_moves_the_next_rows_down() {
+        let csv = format!(
+            "{}\n0,1,1,1,1,1,1,1,\"good\nand crisp\"\n1,1,1,1,1,1,1,x,good\n",
+            HEADER
+        );
+        let errors = validate_csv(&csv);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 4);
+    }
+
+    #[test]
+    fn a_quoted_field_over_several_windows_lines_moves_the_next_rows_down() {
+        let csv = format!(
+            "{}\r\n0,1,1,1,1,1,1,1,\"good\r\nand\r\ncrisp\"\r\n1,1,1,1,1,1,1,x,good\r\n2,1,1,1,1,1,1,1,\r\n",
+            HEADER
+        );
+        let errors = validate_csv(&csv);
+
+        let lines: Vec<u64> = errors.iter().map(|error| error.line).collect();
+        assert_eq!(lines, [5, 6]);
+    }
+
+    #[test]
+    fn old_mac_line_endings_are_counted_too() {
+        let csv = format!("{}\r0,1,1,1,1,1,1,1,good\r1,1,1,1,1,1,1,,good\r", HEADER);
+        let errors = validate_csv(&csv);
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].line, 3);
+    }
+
+    #[test]
+    fn line_endings_split_between_reads_become_one_newline() {
+        let mut converted = String::new();
+        UnixLineEndings::new(b"a\r".chain(&b"\nb\r"[..]).chain(&b"\rc\n"[..]))
+            .read_to_string(&mut converted)
+            .unwrap();
+        assert_eq!(converted, "a\nb\n\nc\n");
+    }
+
+    #[test]
+    fn every_problem_of_a_row_is_reported_on_its_line() {
+        let csv = format!("{}\n,1,1,1,1,1,1,inf,\n", HEADER);
+        let errors = validate_csv(&csv);
+
+        let problems: Vec<_> = errors
+            .iter()
+            .map(|error| (error.line, error.column.as_deref(), error.rejection.kind()))
+            .collect();
+        assert_eq!(
+            problems,
+            vec![
+                (2, Some("A_id"), "missing"),
+                (2, Some("Acidity"), "not finite"),
+                (2, Some("Quality"), "missing"),
+            ]
+        );
+    }
+}
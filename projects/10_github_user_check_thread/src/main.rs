@@ -1,95 +1,118 @@
-use reqwest::blocking::Client as BlockingHttpClient;
-use reqwest::Error;
-// use std::rc::Rc;
-use std::sync::{Arc, Mutex};
+use clap::Parser;
+use github_user_check::{
+    print_invalid_usernames, print_rate_limit_pause, print_report, BlockingChecker, CheckResult,
+    ConcurrencyArgs, InputArgs, LookupArgs, OutcomeCounter, RateLimitArgs, RetryArgs, UserChecker,
+    EXIT_CODES_HELP,
+};
+use std::error::Error;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::thread::{spawn, JoinHandle};
 
-enum GitHubUserSearch {
-    Found(String),
-    NotFound(String),
-}
+#[derive(Debug, Parser)]
+#[command(
+    about = "Check whether GitHub users exist, on a bounded pool of threads",
+    after_help = EXIT_CODES_HELP
+)]
+struct Args {
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[command(flatten)]
+    lookup: LookupArgs,
+
+    #[command(flatten)]
+    retry: RetryArgs,
 
-fn fetch_user(
-    http_client: &BlockingHttpClient,
-    github_username: &str,
-) -> Result<GitHubUserSearch, Error> {
-    let res = http_client
-        .get(format!("https://github.com/{}", github_username))
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/58.0.3029.110 Safari/537.3")
-        .send()
-        .expect("Failed to send request.");
+    #[command(flatten)]
+    rate_limit: RateLimitArgs,
 
-    if res.status().is_success() {
-        Ok(GitHubUserSearch::Found(github_username.to_owned()))
-    } else {
-        Ok(GitHubUserSearch::NotFound(github_username.to_owned()))
+    #[command(flatten)]
+    concurrency: ConcurrencyArgs,
+}
+
+// - returning the error from main would print it with Debug, quoted and
+//   escaped, so it is printed with Display instead
+fn main() -> ExitCode {
+    match run(Args::parse()) {
+        Ok(exit_code) => exit_code,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::FAILURE
+        }
     }
 }
 
-fn main() {
-    let github_usernames = vec![
-        "ericwgreene".to_owned(),
-        "devops-person".to_owned(),
-        "egreene-at-syntrillo".to_owned(),
-        "ericwgreene2".to_owned(),
-    ];
+fn run(args: Args) -> Result<ExitCode, Box<dyn Error>> {
+    // invalid names are reported here and never sent to GitHub
+    let username_list = args.input.username_list()?;
+    print_invalid_usernames(&username_list);
+    let github_usernames = Arc::new(username_list.valid);
 
-    // Arc type is used to share data between threads
-    // Mutex type is used to lock the data
-    // let github_user_found_count = Rc::new(Mutex::new(0));
-    let github_user_found_count = Arc::new(Mutex::new(0));
+    // the counter wraps an Arc<Mutex<OutcomeCounts>>, so every clone moved into
+    // a thread updates the same counts
+    let github_user_outcomes = OutcomeCounter::new();
 
-    let client = BlockingHttpClient::new();
+    // - Arc type is used to share the checker, and its http client, between threads
+    // - the rate limiter is shared too, so the pacing holds over all threads
+    let checker = Arc::new(
+        BlockingChecker::new()
+            .with_timeout(args.lookup.timeout())?
+            .with_lookup(args.lookup.lookup()?)
+            .with_retry_policy(args.retry.policy())
+            .with_rate_limiter(Arc::new(args.rate_limit.limiter())),
+    );
 
-    let github_user_search_threads = github_usernames
-        .into_iter()
-        .map(|github_username| {
-            let client = client.clone();
+    // - no more than --max-in-flight threads are spawned, however many
+    //   usernames there are
+    // - each thread takes the next unchecked username from the shared index
+    //   until none are left, so a slow check does not hold up the others
+    let next_username = Arc::new(AtomicUsize::new(0));
+    let workers = args.concurrency.max_in_flight().min(github_usernames.len());
+
+    let github_user_search_threads = (0..workers)
+        .map(|_| {
             // - pass a reference of the Arc to the Arc::clone method to clone it, then it will moved into the closure
-            // - when spawn executes the closure on a new thread, it will be able to access the Mutex through
+            // - when spawn executes the closure on a new thread, it will be able to use the checker through
             //   the cloned Arc
-            // - when the thread desires to modify the data protected by the Mutex, it will lock the Mutex
-            //   and then modify the data
-            // let github_user_found_count = Rc::clone(&github_user_found_count);
-            let github_user_found_count = Arc::clone(&github_user_found_count);
-            println!("fetching {}", &github_username);
+            let checker = Arc::clone(&checker);
+            let github_usernames = Arc::clone(&github_usernames);
+            let next_username = Arc::clone(&next_username);
+            let github_user_outcomes = github_user_outcomes.clone();
             spawn(move || {
-                let fetch_user_result = fetch_user(&client, &github_username);
-                println!("fetched {}", &github_username);
-                match fetch_user_result {
-                    Ok(GitHubUserSearch::Found(_)) => {
-                        // create mutable reference to the data protected by the Mutex
-                        // lock the Mutex to get the mutable reference, and unwrap the result
-                        let mut count = github_user_found_count.lock().unwrap();
-                        // the * operator is used to dereference the MutexGuard to access the data
-                        *count += 1;
-                    }
-                    _ => {}
+                let mut fetch_user_results = Vec::new();
+                loop {
+                    let index = next_username.fetch_add(1, Ordering::Relaxed);
+                    let Some(github_username) = github_usernames.get(index) else {
+                        break;
+                    };
+                    println!("fetching {}", github_username);
+                    let fetch_user_result = checker.check(github_username);
+                    println!("fetched {}", github_username);
+                    // the limiter only reports a pause, printing it is left to main
+                    print_rate_limit_pause(&fetch_user_result);
+                    github_user_outcomes.record(&fetch_user_result);
+                    fetch_user_results.push((index, fetch_user_result));
                 }
-                fetch_user_result
+                fetch_user_results
             })
         })
-        .collect::<Vec<JoinHandle<Result<GitHubUserSearch, Error>>>>();
+        .collect::<Vec<JoinHandle<Vec<(usize, CheckResult)>>>>();
 
-    let github_user_search_results = github_user_search_threads
+    // the results are put back in the order the usernames were given
+    let mut indexed_results = github_user_search_threads
+        .into_iter()
+        .flat_map(|thread| thread.join().expect("Unable to join thread."))
+        .collect::<Vec<(usize, CheckResult)>>();
+    indexed_results.sort_by_key(|(index, _)| *index);
+    let github_user_search_results = indexed_results
         .into_iter()
-        .map(|thread| thread.join().expect("Unable to join thread."))
-        .collect::<Vec<Result<GitHubUserSearch, Error>>>();
+        .map(|(_, result)| result)
+        .collect::<Vec<CheckResult>>();
 
-    let github_user_final_count = *github_user_found_count.lock().unwrap(); // Lock the Mutex to read the data
-    println!("Number of GitHub users found: {}", github_user_final_count);
+    let counts = github_user_outcomes.counts();
+    print_report(&counts, &github_user_search_results);
 
-    for result in github_user_search_results {
-        match result {
-            Ok(GitHubUserSearch::Found(username)) => {
-                println!("Found GitHub user: {}", username);
-            }
-            Ok(GitHubUserSearch::NotFound(username)) => {
-                println!("GitHub user not found: {}", username);
-            }
-            Err(e) => {
-                println!("Error: {:?}", e);
-            }
-        }
-    }
+    Ok(counts.exit_code(username_list.invalid.len()))
 }
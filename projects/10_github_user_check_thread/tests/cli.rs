@@ -0,0 +1,139 @@
+use github_mock_server::{MockGitHub, MockResponse};
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+use std::time::{Duration, Instant};
+
+fn run(server: &MockGitHub, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_github_user_check_thread"))
+        .args(["--base-url", &server.url(), "--base-delay-ms", "1"])
+        .args(args)
+        .env_remove("GITHUB_TOKEN")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn reports_every_outcome_and_exits_with_the_worst_one() {
+    let server = MockGitHub::start();
+    server.script("/octocat", [MockResponse::found()]);
+    server.script("/hubot", [MockResponse::server_error(500)]);
+
+    let output = run(&server, &["octocat", "ghost", "--max-attempts", "2"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Number of GitHub users found: 1"));
+    assert!(stdout.contains("Found GitHub user: octocat"));
+    assert!(stdout.contains("GitHub user not found: ghost"));
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = run(&server, &["octocat", "hubot", "--max-attempts", "2"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(
+        stdout.contains("Server error 500 Internal Server Error checking hubot (after 2 attempts)")
+    );
+    assert!(stdout.contains("Number of checks failed with a server error: 1"));
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn api_mode_reports_the_user_record() {
+    let server = MockGitHub::start();
+    server.script(
+        "/users/octocat",
+        [MockResponse::api_user("octocat", 583231)],
+    );
+
+    let output = run(&server, &["octocat", "--api"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains(
+        "Found GitHub user: octocat (id 583231, User, created 2011-01-25T18:44:36Z, 8 public repos)"
+    ));
+    assert!(output.status.success());
+}
+
+#[test]
+fn reads_usernames_from_stdin_with_a_dash() {
+    let server = MockGitHub::start();
+    server.script("/octocat", [MockResponse::found()]);
+    server.script("/hubot", [MockResponse::found()]);
+
+    let mut child = Command::new(env!("CARGO_BIN_EXE_github_user_check_thread"))
+        .args(["--base-url", &server.url(), "--file", "-"])
+        .env_remove("GITHUB_TOKEN")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"# team\noctocat\nhubot\nOctocat\n")
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Found GitHub user: octocat"));
+    assert!(stdout.contains("Found GitHub user: hubot"));
+    assert_eq!(server.requests().len(), 2);
+    assert!(output.status.success());
+}
+
+#[test]
+fn rate_limit_pauses_are_reported_on_stderr() {
+    let server = MockGitHub::start();
+    server.script(
+        "/octocat",
+        [MockResponse::rate_limited(1), MockResponse::found()],
+    );
+
+    let output = run(&server, &["octocat"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.contains("GitHub rate limit reached checking octocat, paused all checks for"));
+    assert!(!stdout.contains("rate limit reached"));
+    assert!(stdout.contains("Found GitHub user: octocat (after 2 attempts)"));
+    assert!(output.status.success());
+}
+
+#[test]
+fn no_more_than_max_in_flight_checks_run_at_once() {
+    let server = MockGitHub::start();
+    let usernames = ["alice", "bob", "carol", "dave", "erin", "frank"];
+    for username in usernames {
+        server.script(
+            &format!("/{}", username),
+            [MockResponse::found().with_delay(Duration::from_millis(300))],
+        );
+    }
+
+    // with 2 threads the 6 slow checks take at least 3 rounds
+    let started = Instant::now();
+    let mut args = vec!["--max-in-flight", "2", "--requests-per-second", "1000"];
+    args.extend(usernames);
+    let output = run(&server, &args);
+    assert!(started.elapsed() >= Duration::from_millis(900));
+
+    // the report keeps the order the usernames were given
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let positions: Vec<_> = usernames
+        .iter()
+        .map(|username| {
+            stdout
+                .find(&format!("Found GitHub user: {}", username))
+                .unwrap()
+        })
+        .collect();
+    assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+    assert_eq!(server.requests().len(), 6);
+    assert!(output.status.success());
+}
+
+#[test]
+fn errors_are_printed_without_debug_quoting() {
+    let server = MockGitHub::start();
+
+    let output = run(&server, &[]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("Error: no usernames given, pass them as arguments"));
+    assert_eq!(output.status.code(), Some(1));
+}
@@ -1,79 +1,89 @@
-use reqwest::Client as HttpClient;
-use reqwest::Error;
-use std::sync::{Arc, Mutex};
-use tokio::task::JoinHandle;
+use clap::Parser;
+use futures::stream::{self, StreamExt};
+use github_user_check::{
+    print_counts, print_invalid_usernames, print_rate_limit_pause, print_result, AsyncChecker,
+    AsyncUserChecker, ConcurrencyArgs, InputArgs, LookupArgs, OutcomeCounter, RateLimitArgs,
+    RetryArgs, EXIT_CODES_HELP,
+};
+use std::error::Error;
+use std::process::ExitCode;
+use std::sync::Arc;
 
-enum GitHubUserSearch {
-    Found(String),
-    NotFound(String),
-}
+#[derive(Debug, Parser)]
+#[command(
+    about = "Check whether GitHub users exist, with a bounded number of tokio tasks",
+    after_help = EXIT_CODES_HELP
+)]
+struct Args {
+    #[command(flatten)]
+    input: InputArgs,
 
-async fn fetch_user(
-    http_client: &HttpClient,
-    github_username: &str,
-) -> Result<GitHubUserSearch, Error> {
-    let res = http_client
-        .get(format!("https://github.com/{}", github_username))
-        .header("User-Agent", "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/58.0.3029.110 Safari/537.3")
-        .send()
-        .await?;
+    #[command(flatten)]
+    lookup: LookupArgs,
 
-    if res.status().is_success() {
-        Ok(GitHubUserSearch::Found(github_username.to_owned()))
-    } else {
-        Ok(GitHubUserSearch::NotFound(github_username.to_owned()))
-    }
+    #[command(flatten)]
+    retry: RetryArgs,
+
+    #[command(flatten)]
+    rate_limit: RateLimitArgs,
+
+    #[command(flatten)]
+    concurrency: ConcurrencyArgs,
 }
 
+// - returning the error from main would print it with Debug, quoted and
+//   escaped, so it is printed with Display instead
 #[tokio::main]
-async fn main() {
-    let github_usernames = vec![
-        "ericwgreene".to_owned(),
-        "devops-person".to_owned(),
-        "egreene-at-syntrillo".to_owned(),
-        "ericwgreene2".to_owned(),
-    ];
+async fn main() -> ExitCode {
+    match run(Args::parse()).await {
+        Ok(exit_code) => exit_code,
+        Err(error) => {
+            eprintln!("Error: {}", error);
+            ExitCode::FAILURE
+        }
+    }
+}
 
-    let github_user_found_count = Arc::new(Mutex::new(0));
-    let client = HttpClient::new();
+async fn run(args: Args) -> Result<ExitCode, Box<dyn Error>> {
+    // invalid names are reported here and never sent to GitHub
+    let username_list = args.input.username_list()?;
+    print_invalid_usernames(&username_list);
+    let github_usernames = username_list.valid;
 
-    let mut github_user_search_tasks: Vec<JoinHandle<Result<GitHubUserSearch, Error>>> = vec![];
+    let github_user_outcomes = OutcomeCounter::new();
+    // the clones of the checker given to the tasks share one rate limiter
+    let checker = AsyncChecker::new()
+        .with_timeout(args.lookup.timeout())?
+        .with_lookup(args.lookup.lookup()?)
+        .with_retry_policy(args.retry.policy())
+        .with_rate_limiter(Arc::new(args.rate_limit.limiter()));
 
-    for github_username in github_usernames {
-        let client = client.clone();
-        let github_user_found_count = Arc::clone(&github_user_found_count);
-        println!("fetching {}", &github_username);
-        let task: JoinHandle<Result<GitHubUserSearch, Error>> = tokio::spawn(async move {
-            let fetch_user_result = fetch_user(&client, &github_username).await;
-            println!("fetched {}", &github_username);
-            if let Ok(GitHubUserSearch::Found(_)) = fetch_user_result {
-                let mut count = github_user_found_count.lock().unwrap();
-                *count += 1;
-            }
-            fetch_user_result
-        });
-        github_user_search_tasks.push(task);
-    }
+    // - the stream is lazy, a task is only spawned when buffer_unordered asks for
+    //   the next future, so no more than --max-in-flight tasks exist at a time
+    // - buffer_unordered yields each result as soon as its task completes,
+    //   instead of in the order the usernames were given
+    let mut github_user_search_results = stream::iter(github_usernames)
+        .map(|github_username| {
+            let checker = checker.clone();
+            let github_user_outcomes = github_user_outcomes.clone();
+            println!("fetching {}", &github_username);
+            tokio::spawn(async move {
+                let fetch_user_result = checker.check(&github_username).await;
+                println!("fetched {}", &github_username);
+                // the limiter only reports a pause, printing it is left to main
+                print_rate_limit_pause(&fetch_user_result);
+                github_user_outcomes.record(&fetch_user_result);
+                fetch_user_result
+            })
+        })
+        .buffer_unordered(args.concurrency.max_in_flight());
 
-    let mut github_user_search_results = Vec::new();
-    for task in github_user_search_tasks {
-        github_user_search_results.push(task.await.expect("Task failed."));
+    while let Some(task) = github_user_search_results.next().await {
+        print_result(&task.expect("Task failed."));
     }
 
-    let github_user_final_count = *github_user_found_count.lock().unwrap();
-    println!("Number of GitHub users found: {}", github_user_final_count);
+    let counts = github_user_outcomes.counts();
+    print_counts(&counts);
 
-    for result in github_user_search_results {
-        match result {
-            Ok(GitHubUserSearch::Found(username)) => {
-                println!("Found GitHub user: {}", username);
-            }
-            Ok(GitHubUserSearch::NotFound(username)) => {
-                println!("GitHub user not found: {}", username);
-            }
-            Err(e) => {
-                println!("Error: {:?}", e);
-            }
-        }
-    }
+    Ok(counts.exit_code(username_list.invalid.len()))
 }
@@ -0,0 +1,104 @@
+use github_mock_server::{MockGitHub, MockResponse};
+use std::process::{Command, Output};
+
+fn run(server: &MockGitHub, args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_github_user_check_async"))
+        .args(["--base-url", &server.url(), "--base-delay-ms", "1"])
+        .args(args)
+        .env_remove("GITHUB_TOKEN")
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn reports_every_outcome_and_exits_with_the_worst_one() {
+    let server = MockGitHub::start();
+    server.script("/octocat", [MockResponse::found()]);
+    server.script(
+        "/hubot",
+        [MockResponse::rate_limited(0), MockResponse::found()],
+    );
+
+    let output = run(
+        &server,
+        &["octocat", "hubot", "ghost", "--max-in-flight", "2"],
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Found GitHub user: octocat"));
+    assert!(stdout.contains("Found GitHub user: hubot (after 2 attempts)"));
+    assert!(stdout.contains("GitHub user not found: ghost"));
+    assert!(stdout.contains("Number of GitHub users found: 2"));
+    assert_eq!(output.status.code(), Some(2));
+
+    let output = run(&server, &["octocat", "hubot"]);
+    assert!(output.status.success());
+}
+
+#[test]
+fn api_mode_sends_the_token_from_the_environment() {
+    let server = MockGitHub::start();
+    server.script(
+        "/users/octocat",
+        [MockResponse::api_user("octocat", 583231)],
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_github_user_check_async"))
+        .args([
+            "--base-url",
+            &server.url(),
+            "--api",
+            "--token-env",
+            "MOCK_TOKEN",
+        ])
+        .arg("octocat")
+        .env("MOCK_TOKEN", "secret")
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Found GitHub user: octocat (id 583231"));
+    assert_eq!(
+        server.requests()[0].headers["authorization"],
+        "Bearer secret"
+    );
+}
+
+#[test]
+fn slow_responses_time_out_after_timeout_ms() {
+    let server = MockGitHub::start();
+    server.script(
+        "/octocat",
+        [MockResponse::found().with_delay(std::time::Duration::from_secs(2))],
+    );
+
+    let output = run(
+        &server,
+        &["octocat", "--timeout-ms", "200", "--max-attempts", "1"],
+    );
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Timed out checking octocat"));
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn a_retry_after_too_large_for_a_time_is_not_a_crash() {
+    let server = MockGitHub::start();
+    server.script(
+        "/octocat",
+        [MockResponse::new(429).with_header("retry-after", "18446744073709551615")],
+    );
+
+    let output = run(&server, &["octocat", "--max-attempts", "1"]);
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(stdout.contains("Rate limited checking octocat, the limit resets in"));
+    assert_eq!(output.status.code(), Some(3));
+}
+
+#[test]
+fn errors_are_printed_without_debug_quoting() {
+    let server = MockGitHub::start();
+
+    let output = run(&server, &[]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+    assert!(stderr.starts_with("Error: no usernames given, pass them as arguments"));
+    assert_eq!(output.status.code(), Some(1));
+}
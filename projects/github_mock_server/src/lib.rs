@@ -0,0 +1,249 @@
+use std::collections::{HashMap, VecDeque};
+use std::io::{BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+// - one scripted answer of the mock server
+// - `delay` is waited before anything is written, so a client with a shorter
+//   timeout sees a timeout
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+    pub delay: Duration,
+}
+
+impl MockResponse {
+    pub fn new(status: u16) -> MockResponse {
+        MockResponse {
+            status,
+            headers: Vec::new(),
+            body: String::new(),
+            delay: Duration::ZERO,
+        }
+    }
+
+    // a profile page
+    pub fn found() -> MockResponse {
+        MockResponse::new(200)
+            .with_header("content-type", "text/html")
+            .with_body("<html></html>")
+    }
+
+    // the REST API's record of a user, with the fields the checkers read
+    pub fn api_user(login: &str, id: u64) -> MockResponse {
+        MockResponse::new(200)
+            .with_header("content-type", "application/json")
+            .with_body(&format!(
+                r#"{{"login":"{}","id":{},"type":"User","created_at":"2011-01-25T18:44:36Z","public_repos":8}}"#,
+                login, id
+            ))
+    }
+
+    pub fn not_found() -> MockResponse {
+        MockResponse::new(404)
+    }
+
+    // a 429 that asks the client to wait `retry_after_secs` seconds
+    pub fn rate_limited(retry_after_secs: u64) -> MockResponse {
+        MockResponse::new(429).with_header("retry-after", &retry_after_secs.to_string())
+    }
+
+    pub fn server_error(status: u16) -> MockResponse {
+        MockResponse::new(status)
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> MockResponse {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    pub fn with_body(mut self, body: &str) -> MockResponse {
+        self.body = body.to_owned();
+        self
+    }
+
+    pub fn with_delay(mut self, delay: Duration) -> MockResponse {
+        self.delay = delay;
+        self
+    }
+}
+
+// a request the mock server received, header names are lowercase
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordedRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: HashMap<String, String>,
+}
+
+// - a GitHub stand-in on a random localhost port, answering every path with
+//   the responses scripted for it
+// - each connection is served on its own thread and closed after one response
+// - the server stops when it is dropped
+pub struct MockGitHub {
+    address: SocketAddr,
+    scripts: Arc<Mutex<HashMap<String, VecDeque<MockResponse>>>>,
+    requests: Arc<Mutex<Vec<RecordedRequest>>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl MockGitHub {
+    pub fn start() -> MockGitHub {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("Unable to bind the mock server.");
+        let server = MockGitHub {
+            address: listener.local_addr().unwrap(),
+            scripts: Arc::default(),
+            requests: Arc::default(),
+            stopped: Arc::default(),
+        };
+
+        let (scripts, requests, stopped) = (
+            Arc::clone(&server.scripts),
+            Arc::clone(&server.requests),
+            Arc::clone(&server.stopped),
+        );
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let (scripts, requests) = (Arc::clone(&scripts), Arc::clone(&requests));
+                thread::spawn(move || serve(stream, &scripts, &requests));
+            }
+        });
+
+        server
+    }
+
+    // e.g. http://127.0.0.1:41234, without a trailing slash
+    pub fn url(&self) -> String {
+        format!("http://{}", self.address)
+    }
+
+    // - the responses for `path` are served in order, the last one for every
+    //   request after it
+    // - a path without a script gets a 404
+    pub fn script<I: IntoIterator<Item = MockResponse>>(&self, path: &str, responses: I) {
+        self.scripts
+            .lock()
+            .unwrap()
+            .insert(path.to_owned(), responses.into_iter().collect());
+    }
+
+    pub fn requests(&self) -> Vec<RecordedRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    // the number of requests received for `path`
+    pub fn request_count(&self, path: &str) -> usize {
+        self.requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.path == path)
+            .count()
+    }
+}
+
+impl Drop for MockGitHub {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // the accepting thread only sees the flag once a connection wakes it up
+        let _ = TcpStream::connect(self.address);
+    }
+}
+
+fn serve(
+    stream: TcpStream,
+    scripts: &Mutex<HashMap<String, VecDeque<MockResponse>>>,
+    requests: &Mutex<Vec<RecordedRequest>>,
+) {
+    let Some(request) = read_request(&stream) else {
+        return;
+    };
+
+    let response = {
+        let mut scripts = scripts.lock().unwrap();
+        match scripts.get_mut(&request.path) {
+            Some(script) if script.len() > 1 => script.pop_front(),
+            Some(script) => script.front().cloned(),
+            None => None,
+        }
+    }
+    .unwrap_or_else(MockResponse::not_found);
+    requests.lock().unwrap().push(request);
+
+    thread::sleep(response.delay);
+    // the client may have given up on a slow response already
+    let _ = write_response(stream, &response);
+}
+
+// - reads the request line and the headers, the checkers never send a body
+// - None when the connection closes first, e.g. the wake up in Drop
+fn read_request(stream: &TcpStream) -> Option<RecordedRequest> {
+    let mut reader = BufReader::new(stream);
+    let mut line = String::new();
+    reader.read_line(&mut line).ok()?;
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_owned();
+    let path = parts.next()?.to_owned();
+
+    let mut headers = HashMap::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).ok()? == 0 {
+            return None;
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            headers.insert(name.trim().to_ascii_lowercase(), value.trim().to_owned());
+        }
+    }
+
+    Some(RecordedRequest {
+        method,
+        path,
+        headers,
+    })
+}
+
+fn write_response(mut stream: TcpStream, response: &MockResponse) -> std::io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} {}\r\ncontent-length: {}\r\nconnection: close\r\n",
+        response.status,
+        reason_phrase(response.status),
+        response.body.len()
+    );
+    for (name, value) in &response.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+
+    stream.write_all(head.as_bytes())?;
+    stream.write_all(response.body.as_bytes())?;
+    stream.flush()
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        403 => "Forbidden",
+        404 => "Not Found",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
@@ -0,0 +1,323 @@
+use reqwest::blocking::{Client as BlockingHttpClient, Response as BlockingResponse};
+use reqwest::header::{self, HeaderMap, HeaderValue, InvalidHeaderValue};
+use reqwest::{Client as HttpClient, Response, StatusCode};
+use std::future::Future;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use crate::rate_limit::RateLimiter;
+use crate::retry::RetryPolicy;
+use crate::search::{CheckResult, GitHubUserSearch};
+
+const USER_AGENT: &str = "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/58.0.3029.110 Safari/537.3";
+
+// the REST API rejects requests without a User-Agent, any name will do
+const API_USER_AGENT: &str = "github_user_check";
+const API_VERSION: &str = "2022-11-28";
+
+const PROFILE_BASE_URL: &str = "https://github.com";
+const API_BASE_URL: &str = "https://api.github.com";
+
+// - where a checker looks a user up
+// - the profile page only tells whether the user exists, the REST API also
+//   returns the user's record
+// - `base_url` points the checker at another server, e.g. GitHub Enterprise
+//   or a local mock server in the tests
+#[derive(Clone, Debug)]
+pub enum Lookup {
+    ProfilePage {
+        base_url: String,
+    },
+    // - `authorization` raises the rate limit from 60 to 5000 requests an hour
+    // - the header value is marked sensitive, so Debug does not print the token
+    Api {
+        base_url: String,
+        authorization: Option<HeaderValue>,
+    },
+}
+
+impl Default for Lookup {
+    fn default() -> Self {
+        Lookup::profile_page()
+    }
+}
+
+impl Lookup {
+    pub fn profile_page() -> Lookup {
+        Lookup::ProfilePage {
+            base_url: PROFILE_BASE_URL.to_owned(),
+        }
+    }
+
+    // fails when the token cannot be sent in a header, e.g. it holds a newline
+    pub fn api(token: Option<&str>) -> Result<Lookup, InvalidHeaderValue> {
+        let authorization = match token {
+            Some(token) => {
+                let mut value = HeaderValue::from_str(&format!("Bearer {}", token))?;
+                value.set_sensitive(true);
+                Some(value)
+            }
+            None => None,
+        };
+        Ok(Lookup::Api {
+            base_url: API_BASE_URL.to_owned(),
+            authorization,
+        })
+    }
+
+    pub fn with_base_url(mut self, url: &str) -> Lookup {
+        let (Lookup::ProfilePage { base_url } | Lookup::Api { base_url, .. }) = &mut self;
+        *base_url = url.trim_end_matches('/').to_owned();
+        self
+    }
+
+    fn url(&self, github_username: &str) -> String {
+        match self {
+            Lookup::ProfilePage { base_url } => format!("{}/{}", base_url, github_username),
+            Lookup::Api { base_url, .. } => format!("{}/users/{}", base_url, github_username),
+        }
+    }
+
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        match self {
+            Lookup::ProfilePage { .. } => {
+                headers.insert(header::USER_AGENT, HeaderValue::from_static(USER_AGENT));
+            }
+            Lookup::Api { authorization, .. } => {
+                headers.insert(header::USER_AGENT, HeaderValue::from_static(API_USER_AGENT));
+                headers.insert(
+                    header::ACCEPT,
+                    HeaderValue::from_static("application/vnd.github+json"),
+                );
+                headers.insert(
+                    "x-github-api-version",
+                    HeaderValue::from_static(API_VERSION),
+                );
+                if let Some(authorization) = authorization {
+                    headers.insert(header::AUTHORIZATION, authorization.clone());
+                }
+            }
+        }
+        headers
+    }
+}
+
+// - checks whether a GitHub user exists, blocking the calling thread
+// - Send + Sync so one checker can be shared by many threads
+pub trait UserChecker: Send + Sync {
+    fn check(&self, github_username: &str) -> CheckResult;
+}
+
+// - the async counterpart of UserChecker
+// - the returned future is Send, so checks can run on tokio::spawn'ed tasks
+pub trait AsyncUserChecker: Send + Sync {
+    fn check(&self, github_username: &str) -> impl Future<Output = CheckResult> + Send;
+}
+
+// - looks up a user with a blocking reqwest client
+// - the client keeps its connection pool behind an Arc, so clones are cheap
+//   and share the pool
+// - a failed attempt is retried after sleeping on the calling thread
+// - with a rate limiter, every attempt first waits for a token on the calling
+//   thread
+#[derive(Clone, Debug, Default)]
+pub struct BlockingChecker {
+    client: BlockingHttpClient,
+    lookup: Lookup,
+    retry: RetryPolicy,
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl BlockingChecker {
+    pub fn new() -> BlockingChecker {
+        BlockingChecker::default()
+    }
+
+    // - a request that takes longer ends as a timeout, by default it waits
+    //   for as long as the server keeps the connection open
+    // - fails when the http client cannot be built, e.g. its TLS backend
+    //   does not load
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<BlockingChecker, reqwest::Error> {
+        self.client = BlockingHttpClient::builder().timeout(timeout).build()?;
+        Ok(self)
+    }
+
+    pub fn with_lookup(mut self, lookup: Lookup) -> BlockingChecker {
+        self.lookup = lookup;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> BlockingChecker {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> BlockingChecker {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    // the api's record of a found user is read from the body
+    fn read_response(&self, res: BlockingResponse) -> (GitHubUserSearch, Option<Duration>) {
+        let (search, pause) = read_status(self.limiter.as_deref(), res.status(), res.headers());
+        let search = match search {
+            GitHubUserSearch::Found(_) if matches!(self.lookup, Lookup::Api { .. }) => {
+                res.json().map_or_else(
+                    |e| GitHubUserSearch::from_error(&e),
+                    |user| GitHubUserSearch::Found(Some(user)),
+                )
+            }
+            search => search,
+        };
+        (search, pause)
+    }
+}
+
+impl UserChecker for BlockingChecker {
+    fn check(&self, github_username: &str) -> CheckResult {
+        let mut attempts = 0;
+        let mut rate_limit_pause = None;
+        loop {
+            attempts += 1;
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire_blocking();
+            }
+            let sent = self
+                .client
+                .get(self.lookup.url(github_username))
+                .headers(self.lookup.headers())
+                .send();
+            let search = match sent {
+                Ok(res) => {
+                    let (search, pause) = self.read_response(res);
+                    rate_limit_pause = rate_limit_pause.max(pause);
+                    search
+                }
+                Err(e) => GitHubUserSearch::from_error(&e),
+            };
+
+            match self.retry.retry_delay(attempts, &search) {
+                Some(delay) => thread::sleep(delay),
+                None => {
+                    return CheckResult {
+                        username: github_username.to_owned(),
+                        attempts,
+                        search,
+                        rate_limit_pause,
+                    }
+                }
+            }
+        }
+    }
+}
+
+// - looks up a user with an async reqwest client
+// - a failed attempt is retried after a tokio sleep, so the worker thread
+//   keeps running other tasks in the meantime
+// - the rate limiter is behind an Arc, so the clones moved into tasks share it
+#[derive(Clone, Debug, Default)]
+pub struct AsyncChecker {
+    client: HttpClient,
+    lookup: Lookup,
+    retry: RetryPolicy,
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl AsyncChecker {
+    pub fn new() -> AsyncChecker {
+        AsyncChecker::default()
+    }
+
+    // - a request that takes longer ends as a timeout, by default it waits
+    //   for as long as the server keeps the connection open
+    // - fails when the http client cannot be built, e.g. its TLS backend
+    //   does not load
+    pub fn with_timeout(mut self, timeout: Duration) -> Result<AsyncChecker, reqwest::Error> {
+        self.client = HttpClient::builder().timeout(timeout).build()?;
+        Ok(self)
+    }
+
+    pub fn with_lookup(mut self, lookup: Lookup) -> AsyncChecker {
+        self.lookup = lookup;
+        self
+    }
+
+    pub fn with_retry_policy(mut self, retry: RetryPolicy) -> AsyncChecker {
+        self.retry = retry;
+        self
+    }
+
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> AsyncChecker {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    // the api's record of a found user is read from the body
+    async fn read_response(&self, res: Response) -> (GitHubUserSearch, Option<Duration>) {
+        let (search, pause) = read_status(self.limiter.as_deref(), res.status(), res.headers());
+        let search = match search {
+            GitHubUserSearch::Found(_) if matches!(self.lookup, Lookup::Api { .. }) => {
+                res.json().await.map_or_else(
+                    |e| GitHubUserSearch::from_error(&e),
+                    |user| GitHubUserSearch::Found(Some(user)),
+                )
+            }
+            search => search,
+        };
+        (search, pause)
+    }
+}
+
+impl AsyncUserChecker for AsyncChecker {
+    async fn check(&self, github_username: &str) -> CheckResult {
+        let mut attempts = 0;
+        let mut rate_limit_pause = None;
+        loop {
+            attempts += 1;
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire().await;
+            }
+            let sent = self
+                .client
+                .get(self.lookup.url(github_username))
+                .headers(self.lookup.headers())
+                .send()
+                .await;
+            let search = match sent {
+                Ok(res) => {
+                    let (search, pause) = self.read_response(res).await;
+                    rate_limit_pause = rate_limit_pause.max(pause);
+                    search
+                }
+                Err(e) => GitHubUserSearch::from_error(&e),
+            };
+
+            match self.retry.retry_delay(attempts, &search) {
+                Some(delay) => tokio::time::sleep(delay).await,
+                None => {
+                    return CheckResult {
+                        username: github_username.to_owned(),
+                        attempts,
+                        search,
+                        rate_limit_pause,
+                    }
+                }
+            }
+        }
+    }
+}
+
+// - both checkers read the status and headers of a response the same way
+// - the limiter sees the headers of every response, a found user's included,
+//   since the last request of a window still succeeds
+// - also returns the pause the response started, for CheckResult
+fn read_status(
+    limiter: Option<&RateLimiter>,
+    status: StatusCode,
+    headers: &HeaderMap,
+) -> (GitHubUserSearch, Option<Duration>) {
+    let pause = limiter.and_then(|limiter| limiter.observe(headers));
+    (GitHubUserSearch::from_response(status, headers), pause)
+}
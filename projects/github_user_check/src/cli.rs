@@ -0,0 +1,185 @@
+use clap::Args;
+use std::env;
+use std::error::Error;
+use std::time::Duration;
+
+use crate::checker::Lookup;
+use crate::rate_limit::RateLimiter;
+use crate::retry::RetryPolicy;
+use crate::usernames::{read_username_lines, UsernameList};
+
+// the exit codes of OutcomeCounts::exit_code, for the help of both checkers
+pub const EXIT_CODES_HELP: &str = "Exit codes:
+  0  every user was found
+  1  the usernames or the token could not be read
+  2  a user was not found or a username was invalid
+  3  a check was rate limited or failed";
+
+// the command line options shared by the thread and the async checkers
+#[derive(Debug, Args)]
+pub struct InputArgs {
+    /// GitHub usernames to check
+    usernames: Vec<String>,
+
+    /// Read more usernames from this file, one per line, or from stdin with "-"
+    #[arg(short, long)]
+    file: Option<String>,
+}
+
+impl InputArgs {
+    // the usernames of the arguments and the file, de-duplicated and validated
+    pub fn username_list(&self) -> Result<UsernameList, Box<dyn Error>> {
+        let mut names = self.usernames.clone();
+        if let Some(file) = &self.file {
+            names.extend(read_username_lines(file)?);
+        }
+        if names.is_empty() {
+            return Err(
+                "no usernames given, pass them as arguments, with --file PATH or on stdin with --file -"
+                    .into(),
+            );
+        }
+        Ok(UsernameList::from_names(names))
+    }
+}
+
+// how the thread and the async checkers look users up
+#[derive(Debug, Args)]
+pub struct LookupArgs {
+    /// Look users up with the GitHub REST API and report their id, type,
+    /// creation date and public repos, instead of requesting their profile page
+    #[arg(long)]
+    api: bool,
+
+    /// Environment variable holding the token sent with --api requests, an
+    /// unset or empty variable sends none
+    #[arg(long, default_value = "GITHUB_TOKEN", requires = "api")]
+    token_env: String,
+
+    /// Send the requests to this server instead of github.com, or api.github.com
+    /// with --api, e.g. a GitHub Enterprise host or a local mock server
+    #[arg(long)]
+    base_url: Option<String>,
+
+    /// Milliseconds a request may take before it ends as a timeout and is
+    /// retried
+    #[arg(long, default_value_t = 10_000, value_parser = clap::value_parser!(u64).range(1..))]
+    timeout_ms: u64,
+}
+
+impl LookupArgs {
+    // the token is read here, so it never shows up in the arguments
+    pub fn lookup(&self) -> Result<Lookup, Box<dyn Error>> {
+        let lookup = if self.api {
+            let token = env::var(&self.token_env).unwrap_or_default();
+            let token = Some(token.trim()).filter(|token| !token.is_empty());
+            Lookup::api(token).map_err(|_| {
+                format!(
+                    "the token in {} cannot be sent in an http header",
+                    self.token_env
+                )
+            })?
+        } else {
+            Lookup::profile_page()
+        };
+
+        Ok(match &self.base_url {
+            Some(base_url) => lookup.with_base_url(base_url),
+            None => lookup,
+        })
+    }
+
+    // handed to the checkers' with_timeout
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+// how many users the thread and the async checkers check at the same time
+#[derive(Debug, Args)]
+pub struct ConcurrencyArgs {
+    /// Maximum number of requests in flight at the same time
+    #[arg(long, default_value_t = 8, value_parser = clap::value_parser!(u16).range(1..))]
+    max_in_flight: u16,
+}
+
+impl ConcurrencyArgs {
+    pub fn max_in_flight(&self) -> usize {
+        usize::from(self.max_in_flight)
+    }
+}
+
+// the retry options shared by the thread and the async checkers
+#[derive(Debug, Args)]
+pub struct RetryArgs {
+    /// Maximum number of requests per username, 1 disables retries
+    #[arg(long, default_value_t = RetryPolicy::default().max_attempts, value_parser = clap::value_parser!(u32).range(1..))]
+    max_attempts: u32,
+
+    /// Delay before the first retry in milliseconds, doubled after every retry
+    #[arg(long, default_value_t = RetryPolicy::default().base_delay.as_millis() as u64)]
+    base_delay_ms: u64,
+
+    /// Longest delay between two attempts in milliseconds
+    #[arg(long, default_value_t = RetryPolicy::default().max_delay.as_millis() as u64)]
+    max_delay_ms: u64,
+
+    /// Share of each delay that is random, between 0 and 1
+    #[arg(long, default_value_t = RetryPolicy::default().jitter, value_parser = parse_jitter)]
+    jitter: f64,
+}
+
+impl RetryArgs {
+    pub fn policy(&self) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: self.max_attempts,
+            base_delay: Duration::from_millis(self.base_delay_ms),
+            max_delay: Duration::from_millis(self.max_delay_ms),
+            jitter: self.jitter,
+        }
+    }
+}
+
+// the client-side pacing shared by the thread and the async checkers
+#[derive(Debug, Args)]
+pub struct RateLimitArgs {
+    /// Maximum number of requests per second over all threads or tasks, 0 only
+    /// waits when GitHub's rate limit headers ask for it
+    #[arg(long, default_value_t = 10.0, value_parser = parse_requests_per_second)]
+    requests_per_second: f64,
+
+    /// Number of requests that may be sent at once before the pacing starts
+    #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u32).range(1..))]
+    burst: u32,
+}
+
+impl RateLimitArgs {
+    pub fn limiter(&self) -> RateLimiter {
+        RateLimiter::new(Some(self.requests_per_second), self.burst)
+    }
+}
+
+fn parse_requests_per_second(value: &str) -> Result<f64, String> {
+    let rate: f64 = value
+        .parse()
+        .map_err(|_| format!("{:?} is not a number", value))?;
+    if rate >= 0.0 && rate.is_finite() {
+        Ok(rate)
+    } else {
+        Err(format!("the rate must be 0 or more, not {}", rate))
+    }
+}
+
+fn parse_jitter(value: &str) -> Result<f64, String> {
+    let jitter: f64 = value
+        .parse()
+        .map_err(|_| format!("{:?} is not a number", value))?;
+    if (0.0..=1.0).contains(&jitter) {
+        Ok(jitter)
+    } else {
+        Err(format!(
+            "the jitter must be between 0 and 1, not {}",
+            jitter
+        ))
+    }
+}
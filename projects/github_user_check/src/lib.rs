@@ -0,0 +1,23 @@
+pub mod checker;
+pub mod cli;
+pub mod rate_limit;
+pub mod report;
+pub mod retry;
+pub mod search;
+pub mod usernames;
+
+pub use crate::checker::{AsyncChecker, AsyncUserChecker, BlockingChecker, Lookup, UserChecker};
+pub use crate::cli::{
+    ConcurrencyArgs, InputArgs, LookupArgs, RateLimitArgs, RetryArgs, EXIT_CODES_HELP,
+};
+pub use crate::rate_limit::RateLimiter;
+pub use crate::report::{
+    print_counts, print_invalid_usernames, print_rate_limit_pause, print_report, print_result,
+    OutcomeCounter, OutcomeCounts,
+};
+pub use crate::retry::RetryPolicy;
+pub use crate::search::{CheckResult, GitHubUser, GitHubUserSearch, MAX_RATE_LIMIT_WAIT};
+pub use crate::usernames::{
+    read_username_lines, validate_username, InvalidReason, InvalidUsername, UsernameList,
+    MAX_USERNAME_LENGTH,
+};
@@ -0,0 +1,155 @@
+use reqwest::header::HeaderMap;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::search::{rate_limit_exhausted, rate_limit_reset, MAX_RATE_LIMIT_WAIT};
+
+// - a token bucket shared by every thread or task of a checker, behind an Arc
+// - each request takes one token, the bucket holds at most `burst` tokens and
+//   refills at `requests_per_second`
+// - when a response says the rate limit is used up, every request waits until
+//   GitHub's window resets, whatever tokens are left
+#[derive(Debug)]
+pub struct RateLimiter {
+    // None only honors GitHub's headers
+    requests_per_second: Option<f64>,
+    burst: f64,
+    state: Mutex<BucketState>,
+}
+
+#[derive(Debug)]
+struct BucketState {
+    tokens: f64,
+    refilled_at: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl RateLimiter {
+    // the bucket starts full, so the first `burst` requests go out at once
+    pub fn new(requests_per_second: Option<f64>, burst: u32) -> RateLimiter {
+        let burst = f64::from(burst.max(1));
+        RateLimiter {
+            requests_per_second: requests_per_second.filter(|rate| *rate > 0.0),
+            burst,
+            state: Mutex::new(BucketState {
+                tokens: burst,
+                refilled_at: Instant::now(),
+                paused_until: None,
+            }),
+        }
+    }
+
+    // sends requests as fast as they come, until GitHub asks to slow down
+    pub fn unlimited() -> RateLimiter {
+        RateLimiter::new(None, 1)
+    }
+
+    // waits on the calling thread until a request may be sent
+    pub fn acquire_blocking(&self) {
+        while let Err(wait) = self.try_acquire() {
+            thread::sleep(wait);
+        }
+    }
+
+    // waits on a tokio timer until a request may be sent
+    pub async fn acquire(&self) {
+        while let Err(wait) = self.try_acquire() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    // - pauses every request until the reset time of the response, when it
+    //   says no requests remain or sends a Retry-After
+    // - a pause only ever gets longer, an older response cannot shorten it
+    // - returns the wait when this response started or lengthened the pause,
+    //   so the caller can tell the user
+    pub fn observe(&self, headers: &HeaderMap) -> Option<Duration> {
+        if !rate_limit_exhausted(headers) {
+            return None;
+        }
+        let wait = rate_limit_reset(headers)?
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO)
+            .min(MAX_RATE_LIMIT_WAIT);
+        let until = Instant::now().checked_add(wait)?;
+
+        let mut state = self.state.lock().unwrap();
+        if state.paused_until.is_none_or(|paused| paused < until) {
+            state.paused_until = Some(until);
+            Some(wait)
+        } else {
+            None
+        }
+    }
+
+    // takes a token, or returns how long to wait before trying again
+    fn try_acquire(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+
+        if let Some(paused_until) = state.paused_until {
+            if now < paused_until {
+                return Err(paused_until - now);
+            }
+            state.paused_until = None;
+        }
+
+        let Some(rate) = self.requests_per_second else {
+            return Ok(());
+        };
+        let elapsed = now.duration_since(state.refilled_at).as_secs_f64();
+        state.tokens = (state.tokens + elapsed * rate).min(self.burst);
+        state.refilled_at = now;
+
+        if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - state.tokens) / rate))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn retry_after(seconds: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("retry-after", HeaderValue::from_str(seconds).unwrap());
+        headers
+    }
+
+    #[test]
+    fn only_a_longer_pause_is_reported() {
+        let limiter = RateLimiter::unlimited();
+
+        assert_eq!(limiter.observe(&HeaderMap::new()), None);
+        let wait = limiter.observe(&retry_after("30")).unwrap();
+        assert!(wait > Duration::from_secs(28) && wait <= Duration::from_secs(30));
+        assert_eq!(limiter.observe(&retry_after("10")), None);
+        assert!(limiter.observe(&retry_after("60")).is_some());
+    }
+
+    #[test]
+    fn a_huge_retry_after_pauses_for_the_longest_trusted_wait() {
+        let limiter = RateLimiter::unlimited();
+        let wait = limiter.observe(&retry_after("18446744073709551615"));
+
+        assert!(wait.is_some_and(|wait| wait <= MAX_RATE_LIMIT_WAIT
+            && wait > MAX_RATE_LIMIT_WAIT - Duration::from_secs(2)));
+        assert!(limiter.try_acquire().is_err());
+    }
+
+    #[test]
+    fn the_bucket_refills_at_the_rate() {
+        let limiter = RateLimiter::new(Some(10.0), 2);
+
+        assert_eq!(limiter.try_acquire(), Ok(()));
+        assert_eq!(limiter.try_acquire(), Ok(()));
+        let wait = limiter.try_acquire().unwrap_err();
+        assert!(wait > Duration::from_millis(90) && wait <= Duration::from_millis(100));
+    }
+}
@@ -0,0 +1,248 @@
+use std::process::ExitCode;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use crate::search::{CheckResult, GitHubUserSearch};
+use crate::usernames::UsernameList;
+
+// the number of checks that ended with each kind of outcome
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct OutcomeCounts {
+    pub found: usize,
+    pub not_found: usize,
+    pub rate_limited: usize,
+    pub server_errors: usize,
+    pub unexpected_statuses: usize,
+    pub timeouts: usize,
+    pub transport_errors: usize,
+    pub invalid_responses: usize,
+}
+
+impl OutcomeCounts {
+    // the checks that could not tell whether the user exists
+    pub fn failed(&self) -> usize {
+        self.rate_limited
+            + self.server_errors
+            + self.unexpected_statuses
+            + self.timeouts
+            + self.transport_errors
+            + self.invalid_responses
+    }
+
+    // - 0 when every user was found
+    // - 2 when a user was not found or a username was invalid
+    // - 3 when a check failed, whatever the other checks found
+    // - 1 is left to the errors main returns, e.g. an unreadable file
+    pub fn exit_code(&self, invalid_usernames: usize) -> ExitCode {
+        if self.failed() > 0 {
+            ExitCode::from(3)
+        } else if self.not_found > 0 || invalid_usernames > 0 {
+            ExitCode::from(2)
+        } else {
+            ExitCode::SUCCESS
+        }
+    }
+}
+
+// - counts the outcomes of checks running on many threads or tasks
+// - Arc type is used to share the counts between threads, Mutex type is used
+//   to lock them, so cloning a counter shares the same counts
+#[derive(Clone, Debug, Default)]
+pub struct OutcomeCounter {
+    counts: Arc<Mutex<OutcomeCounts>>,
+}
+
+impl OutcomeCounter {
+    pub fn new() -> OutcomeCounter {
+        OutcomeCounter::default()
+    }
+
+    pub fn record(&self, result: &CheckResult) {
+        // lock the Mutex to get a mutable reference to the counts, the MutexGuard
+        // dereferences to them, so their fields can be updated through it
+        let mut counts = self.counts.lock().unwrap();
+        match result.search {
+            GitHubUserSearch::Found(_) => counts.found += 1,
+            GitHubUserSearch::NotFound => counts.not_found += 1,
+            GitHubUserSearch::RateLimited { .. } => counts.rate_limited += 1,
+            GitHubUserSearch::ServerError(_) => counts.server_errors += 1,
+            GitHubUserSearch::UnexpectedStatus(_) => counts.unexpected_statuses += 1,
+            GitHubUserSearch::Timeout => counts.timeouts += 1,
+            GitHubUserSearch::Transport(_) => counts.transport_errors += 1,
+            GitHubUserSearch::InvalidResponse(_) => counts.invalid_responses += 1,
+        }
+    }
+
+    pub fn counts(&self) -> OutcomeCounts {
+        *self.counts.lock().unwrap()
+    }
+}
+
+// prints the outcome counts, then one line per checked username
+pub fn print_report(counts: &OutcomeCounts, results: &[CheckResult]) {
+    print_counts(counts);
+
+    for result in results {
+        print_result(result);
+    }
+}
+
+// - prints the number of users found and not found
+// - the failed checks are only listed when there are some
+pub fn print_counts(counts: &OutcomeCounts) {
+    println!("Number of GitHub users found: {}", counts.found);
+    println!("Number of GitHub users not found: {}", counts.not_found);
+
+    for (count, outcome) in [
+        (counts.rate_limited, "rate limited"),
+        (counts.server_errors, "failed with a server error"),
+        (
+            counts.unexpected_statuses,
+            "failed with an unexpected status",
+        ),
+        (counts.timeouts, "timed out"),
+        (counts.transport_errors, "failed with a network error"),
+        (counts.invalid_responses, "got an unreadable response"),
+    ] {
+        if count > 0 {
+            println!("Number of checks {}: {}", outcome, count);
+        }
+    }
+}
+
+// - prints one line for a checked username
+// - the number of attempts is only shown when the check was retried
+pub fn print_result(result: &CheckResult) {
+    let username = &result.username;
+    let attempts = if result.attempts > 1 {
+        format!(" (after {} attempts)", result.attempts)
+    } else {
+        String::new()
+    };
+    match &result.search {
+        GitHubUserSearch::Found(None) => {
+            println!("Found GitHub user: {}{}", username, attempts);
+        }
+        GitHubUserSearch::Found(Some(user)) => {
+            println!(
+                "Found GitHub user: {}{} (id {}, {}, created {}, {} public repos)",
+                user.login,
+                attempts,
+                user.id,
+                user.account_type,
+                user.created_at,
+                user.public_repos
+            );
+        }
+        GitHubUserSearch::NotFound => {
+            println!("GitHub user not found: {}{}", username, attempts);
+        }
+        GitHubUserSearch::RateLimited { reset } => {
+            // the reset time may already have passed while the report was printed
+            let reset = match reset.map(|reset| reset.duration_since(SystemTime::now())) {
+                Some(Ok(wait)) => format!(", the limit resets in {}s", wait.as_secs()),
+                Some(Err(_)) => ", the limit has reset".to_owned(),
+                None => String::new(),
+            };
+            println!("Rate limited checking {}{}{}", username, attempts, reset);
+        }
+        GitHubUserSearch::ServerError(status) => {
+            println!("Server error {} checking {}{}", status, username, attempts);
+        }
+        GitHubUserSearch::UnexpectedStatus(status) => {
+            println!(
+                "Unexpected status {} checking {}{}",
+                status, username, attempts
+            );
+        }
+        GitHubUserSearch::Timeout => {
+            println!("Timed out checking {}{}", username, attempts);
+        }
+        GitHubUserSearch::Transport(error) => {
+            println!("Network error checking {}{}: {}", username, attempts, error);
+        }
+        GitHubUserSearch::InvalidResponse(error) => {
+            println!(
+                "Unreadable response checking {}{}: {}",
+                username, attempts, error
+            );
+        }
+    }
+}
+
+// - tells the user when a check made every check wait for GitHub's rate limit
+// - printed on stderr, next to the progress of the checks
+pub fn print_rate_limit_pause(result: &CheckResult) {
+    if let Some(pause) = result.rate_limit_pause {
+        eprintln!(
+            "GitHub rate limit reached checking {}, paused all checks for {:.1}s",
+            result.username,
+            pause.as_secs_f64()
+        );
+    }
+}
+
+// reports the usernames that were dropped before any request was sent
+pub fn print_invalid_usernames(list: &UsernameList) {
+    for invalid in &list.invalid {
+        println!(
+            "Invalid GitHub username: {} ({})",
+            invalid.username, invalid.reason
+        );
+    }
+    if list.duplicates > 0 {
+        println!("Skipped {} duplicate usernames", list.duplicates);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts_of(searches: &[GitHubUserSearch]) -> OutcomeCounts {
+        let counter = OutcomeCounter::new();
+        for search in searches {
+            counter.record(&CheckResult {
+                username: "octocat".to_owned(),
+                attempts: 1,
+                search: search.clone(),
+                rate_limit_pause: None,
+            });
+        }
+        counter.counts()
+    }
+
+    #[test]
+    fn every_outcome_is_counted_once() {
+        let counts = counts_of(&[
+            GitHubUserSearch::Found(None),
+            GitHubUserSearch::Found(None),
+            GitHubUserSearch::NotFound,
+            GitHubUserSearch::RateLimited { reset: None },
+            GitHubUserSearch::Timeout,
+            GitHubUserSearch::Transport("refused".to_owned()),
+        ]);
+
+        assert_eq!(counts.found, 2);
+        assert_eq!(counts.not_found, 1);
+        assert_eq!(counts.failed(), 3);
+    }
+
+    #[test]
+    fn the_exit_code_is_the_worst_outcome() {
+        let found = counts_of(&[GitHubUserSearch::Found(None)]);
+        let not_found = counts_of(&[GitHubUserSearch::Found(None), GitHubUserSearch::NotFound]);
+        let failed = counts_of(&[
+            GitHubUserSearch::NotFound,
+            GitHubUserSearch::ServerError(reqwest::StatusCode::BAD_GATEWAY),
+        ]);
+
+        assert_eq!(found.exit_code(0), ExitCode::SUCCESS);
+        assert_eq!(found.exit_code(1), ExitCode::from(2));
+        assert_eq!(not_found.exit_code(0), ExitCode::from(2));
+        assert_eq!(failed.exit_code(0), ExitCode::from(3));
+        assert_eq!(failed.exit_code(4), ExitCode::from(3));
+        // nothing checked and nothing invalid is not a failure
+        assert_eq!(OutcomeCounts::default().exit_code(0), ExitCode::SUCCESS);
+    }
+}
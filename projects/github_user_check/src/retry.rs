@@ -0,0 +1,167 @@
+use rand::Rng;
+use std::time::Duration;
+
+use crate::search::GitHubUserSearch;
+
+// - how often and how long to wait before checking a username again
+// - the delay doubles after every failed attempt, starting at `base_delay`
+//   and capped at `max_delay`
+// - `jitter` is the share of the delay that is random, 0.5 waits between half
+//   and all of it, so checks that failed together do not retry together
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    // the first request included, 1 never retries
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub jitter: f64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            jitter: 0.5,
+        }
+    }
+}
+
+impl RetryPolicy {
+    // sends every request once
+    pub fn none() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 1,
+            ..RetryPolicy::default()
+        }
+    }
+
+    // - a rate limit, a server error, a timeout or a failed connection may
+    //   succeed a moment later
+    // - found, not found, an unexpected status and a body that is not a user
+    //   record are the same every time
+    pub fn is_retryable(search: &GitHubUserSearch) -> bool {
+        matches!(
+            search,
+            GitHubUserSearch::RateLimited { .. }
+                | GitHubUserSearch::ServerError(_)
+                | GitHubUserSearch::Timeout
+                | GitHubUserSearch::Transport(_)
+        )
+    }
+
+    // - how long to wait before the next attempt, after `attempts` attempts
+    //   ended with `search`
+    // - None when the search is final or the attempts are used up
+    pub fn retry_delay(&self, attempts: u32, search: &GitHubUserSearch) -> Option<Duration> {
+        if RetryPolicy::is_retryable(search) && attempts < self.max_attempts {
+            Some(self.backoff(attempts))
+        } else {
+            None
+        }
+    }
+
+    fn backoff(&self, attempts: u32) -> Duration {
+        let doublings = attempts.saturating_sub(1).min(31);
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << doublings)
+            .min(self.max_delay);
+        let jitter = self.jitter.clamp(0.0, 1.0) * rand::thread_rng().gen::<f64>();
+        delay.mul_f64(1.0 - jitter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(jitter: f64) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 40,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(2),
+            jitter,
+        }
+    }
+
+    #[test]
+    fn the_delay_doubles_up_to_the_maximum() {
+        let policy = policy(0.0);
+        let delays: Vec<Duration> = (1..=6).map(|attempts| policy.backoff(attempts)).collect();
+
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1600, 2000].map(Duration::from_millis)
+        );
+        // far more doublings than a duration holds still stop at the maximum
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn jitter_waits_between_the_random_share_and_the_whole_delay() {
+        let (jittered, exact) = (policy(0.5), policy(0.0));
+        for attempts in 1..=6 {
+            let full = exact.backoff(attempts);
+            for _ in 0..100 {
+                let delay = jittered.backoff(attempts);
+                assert!(
+                    delay <= full && delay >= full / 2,
+                    "{:?} of {:?}",
+                    delay,
+                    full
+                );
+            }
+        }
+
+        // a jitter outside of 0 and 1 is clamped, it never waits less than nothing
+        assert!(policy(7.0).backoff(3) <= Duration::from_millis(400));
+        assert_eq!(policy(-1.0).backoff(3), Duration::from_millis(400));
+    }
+
+    #[test]
+    fn only_failures_that_may_pass_are_retried() {
+        let policy = policy(0.0);
+        let server_error = GitHubUserSearch::ServerError(reqwest::StatusCode::BAD_GATEWAY);
+
+        assert_eq!(
+            policy.retry_delay(1, &server_error),
+            Some(Duration::from_millis(100))
+        );
+        assert!(policy
+            .retry_delay(2, &GitHubUserSearch::RateLimited { reset: None })
+            .is_some());
+        assert!(policy.retry_delay(1, &GitHubUserSearch::Timeout).is_some());
+        assert!(policy
+            .retry_delay(1, &GitHubUserSearch::Transport("refused".to_owned()))
+            .is_some());
+
+        assert_eq!(policy.retry_delay(1, &GitHubUserSearch::NotFound), None);
+        assert_eq!(policy.retry_delay(1, &GitHubUserSearch::Found(None)), None);
+        assert_eq!(
+            policy.retry_delay(
+                1,
+                &GitHubUserSearch::UnexpectedStatus(reqwest::StatusCode::FORBIDDEN)
+            ),
+            None
+        );
+        assert_eq!(
+            policy.retry_delay(1, &GitHubUserSearch::InvalidResponse("x".to_owned())),
+            None
+        );
+    }
+
+    #[test]
+    fn the_attempts_run_out() {
+        let server_error = GitHubUserSearch::ServerError(reqwest::StatusCode::BAD_GATEWAY);
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            ..policy(0.0)
+        };
+
+        assert!(policy.retry_delay(2, &server_error).is_some());
+        assert_eq!(policy.retry_delay(3, &server_error), None);
+        assert_eq!(RetryPolicy::none().retry_delay(1, &server_error), None);
+    }
+}
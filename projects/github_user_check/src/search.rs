@@ -0,0 +1,183 @@
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+use serde::Deserialize;
+use std::error::Error;
+use std::time::{Duration, SystemTime};
+
+// the fields of the REST API's user record that the report shows
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct GitHubUser {
+    pub login: String,
+    pub id: u64,
+    // "User" or "Organization"
+    #[serde(rename = "type")]
+    pub account_type: String,
+    // ISO 8601, e.g. "2011-01-25T18:44:36Z"
+    pub created_at: String,
+    pub public_repos: u64,
+}
+
+// - what one check found out about a username
+// - only a 404 means the user does not exist, the other failures say nothing
+//   about the user
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum GitHubUserSearch {
+    // the user's record when it was looked up with the REST API
+    Found(Option<GitHubUser>),
+    NotFound,
+    // - a 429, or a 403 with no requests remaining or a Retry-After header
+    // - `reset` is when GitHub accepts requests again, when it said so
+    RateLimited { reset: Option<SystemTime> },
+    ServerError(StatusCode),
+    // any other status, e.g. a 403 that is not about the rate limit
+    UnexpectedStatus(StatusCode),
+    Timeout,
+    // the request never got a response, e.g. the connection failed
+    Transport(String),
+    // the body of the response could not be read as a user record
+    InvalidResponse(String),
+}
+
+impl GitHubUserSearch {
+    // - reads the status and the rate limit headers of a response
+    // - both checkers read the response the same way
+    pub fn from_response(status: StatusCode, headers: &HeaderMap) -> GitHubUserSearch {
+        match status {
+            _ if status.is_success() => GitHubUserSearch::Found(None),
+            StatusCode::NOT_FOUND => GitHubUserSearch::NotFound,
+            StatusCode::TOO_MANY_REQUESTS => GitHubUserSearch::RateLimited {
+                reset: rate_limit_reset(headers),
+            },
+            StatusCode::FORBIDDEN if rate_limit_exhausted(headers) => {
+                GitHubUserSearch::RateLimited {
+                    reset: rate_limit_reset(headers),
+                }
+            }
+            _ if status.is_server_error() => GitHubUserSearch::ServerError(status),
+            _ => GitHubUserSearch::UnexpectedStatus(status),
+        }
+    }
+
+    pub fn from_error(error: &reqwest::Error) -> GitHubUserSearch {
+        if error.is_timeout() {
+            return GitHubUserSearch::Timeout;
+        }
+
+        // - reqwest's own message only names the url, the cause is in the sources
+        // - some sources repeat the message of their own source, it is kept once
+        let mut message = error.to_string();
+        let mut source = error.source();
+        while let Some(cause) = source {
+            let cause_message = cause.to_string();
+            if !message.contains(&cause_message) {
+                message = format!("{}: {}", message, cause_message);
+            }
+            source = cause.source();
+        }
+        if error.is_decode() {
+            GitHubUserSearch::InvalidResponse(message)
+        } else {
+            GitHubUserSearch::Transport(message)
+        }
+    }
+}
+
+fn header_number(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.trim().parse().ok()
+}
+
+// true when GitHub asks for no more requests until the rate limit resets
+pub(crate) fn rate_limit_exhausted(headers: &HeaderMap) -> bool {
+    header_number(headers, "x-ratelimit-remaining") == Some(0)
+        || header_number(headers, "retry-after").is_some()
+}
+
+// the longest wait a rate limit header is trusted with, GitHub's windows last
+// an hour
+pub const MAX_RATE_LIMIT_WAIT: Duration = Duration::from_secs(60 * 60);
+
+// - Retry-After is a number of seconds from now, X-RateLimit-Reset the unix
+//   time the limit resets at
+// - Retry-After wins, GitHub sends it for its secondary rate limits
+// - a reset further away than MAX_RATE_LIMIT_WAIT is moved to that limit, a
+//   time that does not fit in a SystemTime is ignored
+pub(crate) fn rate_limit_reset(headers: &HeaderMap) -> Option<SystemTime> {
+    let now = SystemTime::now();
+    let latest = now.checked_add(MAX_RATE_LIMIT_WAIT)?;
+    let reset = match header_number(headers, "retry-after") {
+        Some(seconds) => now.checked_add(Duration::from_secs(seconds).min(MAX_RATE_LIMIT_WAIT)),
+        None => SystemTime::UNIX_EPOCH.checked_add(Duration::from_secs(header_number(
+            headers,
+            "x-ratelimit-reset",
+        )?)),
+    }?;
+    Some(reset.min(latest))
+}
+
+// the outcome of checking one username
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckResult {
+    pub username: String,
+    // number of requests sent, 1 when the first one was answered
+    pub attempts: u32,
+    // the outcome of the last attempt
+    pub search: GitHubUserSearch,
+    // the longest pause of every check that a response of this one started,
+    // when GitHub said its rate limit was used up
+    pub rate_limit_pause: Option<Duration>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        pairs
+            .iter()
+            .map(|(name, value)| (*name, value.parse().unwrap()))
+            .map(|(name, value)| (reqwest::header::HeaderName::from_static(name), value))
+            .collect()
+    }
+
+    #[test]
+    fn retry_after_is_counted_from_now_and_wins() {
+        let before = SystemTime::now();
+        let reset = rate_limit_reset(&headers(&[
+            ("retry-after", "30"),
+            ("x-ratelimit-reset", "1700000000"),
+        ]))
+        .unwrap();
+
+        let wait = reset.duration_since(before).unwrap();
+        assert!(wait >= Duration::from_secs(30) && wait < Duration::from_secs(31));
+    }
+
+    #[test]
+    fn huge_resets_are_capped_instead_of_overflowing() {
+        for pairs in [
+            [("retry-after", "18446744073709551615")],
+            [("x-ratelimit-reset", "18446744073709551615")],
+            [("x-ratelimit-reset", "99999999999")],
+        ] {
+            let before = SystemTime::now();
+            let reset = rate_limit_reset(&headers(&pairs));
+            // the reset is either capped to an hour from now or ignored
+            assert!(reset.is_none_or(|reset| {
+                reset.duration_since(before).unwrap()
+                    <= MAX_RATE_LIMIT_WAIT + Duration::from_secs(1)
+            }));
+        }
+        let reset = rate_limit_reset(&headers(&[("retry-after", "18446744073709551615")]));
+        assert!(reset.is_some());
+    }
+
+    #[test]
+    fn a_past_reset_is_kept_as_given() {
+        let reset = rate_limit_reset(&headers(&[("x-ratelimit-reset", "1700000000")]));
+        assert_eq!(
+            reset,
+            Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        );
+        assert_eq!(rate_limit_reset(&headers(&[])), None);
+    }
+}
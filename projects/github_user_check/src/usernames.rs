@@ -0,0 +1,193 @@
+use std::collections::HashSet;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+// GitHub rejects usernames longer than this
+pub const MAX_USERNAME_LENGTH: usize = 39;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InvalidReason {
+    TooLong,
+    InvalidCharacter(char),
+    LeadingOrTrailingHyphen,
+    ConsecutiveHyphens,
+}
+
+impl fmt::Display for InvalidReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidReason::TooLong => {
+                write!(f, "longer than {} characters", MAX_USERNAME_LENGTH)
+            }
+            InvalidReason::InvalidCharacter(c) => write!(f, "{:?} is not allowed", c),
+            InvalidReason::LeadingOrTrailingHyphen => {
+                write!(f, "cannot begin or end with a hyphen")
+            }
+            InvalidReason::ConsecutiveHyphens => write!(f, "cannot contain consecutive hyphens"),
+        }
+    }
+}
+
+// - GitHub usernames are made of ascii letters, digits and single hyphens
+// - they cannot begin or end with a hyphen and are at most 39 characters long
+pub fn validate_username(username: &str) -> Result<(), InvalidReason> {
+    if let Some(c) = username
+        .chars()
+        .find(|c| !c.is_ascii_alphanumeric() && *c != '-')
+    {
+        return Err(InvalidReason::InvalidCharacter(c));
+    }
+    if username.len() > MAX_USERNAME_LENGTH {
+        return Err(InvalidReason::TooLong);
+    }
+    if username.starts_with('-') || username.ends_with('-') {
+        return Err(InvalidReason::LeadingOrTrailingHyphen);
+    }
+    if username.contains("--") {
+        return Err(InvalidReason::ConsecutiveHyphens);
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InvalidUsername {
+    pub username: String,
+    pub reason: InvalidReason,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct UsernameList {
+    // the usernames to check, in the order they were first given
+    pub valid: Vec<String>,
+    // the usernames that are never sent to GitHub
+    pub invalid: Vec<InvalidUsername>,
+    // number of usernames dropped because they were already in the list
+    pub duplicates: usize,
+}
+
+impl UsernameList {
+    // - surrounding whitespace and empty names are ignored
+    // - GitHub usernames are case-insensitive, so the first spelling of a name
+    //   is kept and the later ones count as duplicates
+    pub fn from_names<I, S>(names: I) -> UsernameList
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut list = UsernameList::default();
+        let mut seen = HashSet::new();
+
+        for name in names {
+            let name = name.as_ref().trim();
+            if name.is_empty() {
+                continue;
+            }
+            if !seen.insert(name.to_ascii_lowercase()) {
+                list.duplicates += 1;
+                continue;
+            }
+            match validate_username(name) {
+                Ok(()) => list.valid.push(name.to_owned()),
+                Err(reason) => list.invalid.push(InvalidUsername {
+                    username: name.to_owned(),
+                    reason,
+                }),
+            }
+        }
+        list
+    }
+}
+
+// - reads one username per line, lines starting with # are comments
+// - a path of "-" reads from stdin
+pub fn read_username_lines<P: AsRef<Path>>(file_path: P) -> Result<Vec<String>, Box<dyn Error>> {
+    if file_path.as_ref() == Path::new("-") {
+        read_usernames(io::stdin().lock())
+    } else {
+        read_usernames(BufReader::new(File::open(file_path)?))
+    }
+}
+
+fn read_usernames<R: BufRead>(reader: R) -> Result<Vec<String>, Box<dyn Error>> {
+    let mut names = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if !line.trim_start().starts_with('#') {
+            names.push(line);
+        }
+    }
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usernames_follow_github_rules() {
+        assert_eq!(validate_username("octocat"), Ok(()));
+        assert_eq!(validate_username("mona-lisa-42"), Ok(()));
+        assert_eq!(validate_username(&"a".repeat(MAX_USERNAME_LENGTH)), Ok(()));
+        assert_eq!(
+            validate_username(&"a".repeat(MAX_USERNAME_LENGTH + 1)),
+            Err(InvalidReason::TooLong)
+        );
+        assert_eq!(
+            validate_username("octo_cat"),
+            Err(InvalidReason::InvalidCharacter('_'))
+        );
+        assert_eq!(
+            validate_username("ünïcode"),
+            Err(InvalidReason::InvalidCharacter('ü'))
+        );
+        assert_eq!(
+            validate_username("-octocat"),
+            Err(InvalidReason::LeadingOrTrailingHyphen)
+        );
+        assert_eq!(
+            validate_username("octocat-"),
+            Err(InvalidReason::LeadingOrTrailingHyphen)
+        );
+        assert_eq!(
+            validate_username("octo--cat"),
+            Err(InvalidReason::ConsecutiveHyphens)
+        );
+    }
+
+    #[test]
+    fn the_first_spelling_of_a_name_is_kept() {
+        let list = UsernameList::from_names([
+            " octocat ",
+            "OctoCat",
+            "",
+            "bad_name",
+            "hubot",
+            "BAD_NAME",
+            "octocat",
+        ]);
+
+        assert_eq!(list.valid, ["octocat", "hubot"]);
+        assert_eq!(
+            list.invalid,
+            [InvalidUsername {
+                username: "bad_name".to_owned(),
+                reason: InvalidReason::InvalidCharacter('_'),
+            }]
+        );
+        assert_eq!(list.duplicates, 3);
+    }
+
+    #[test]
+    fn comment_lines_are_skipped() {
+        let names = read_usernames("# team\noctocat\n  # away\n\nhubot\r\n".as_bytes()).unwrap();
+        assert_eq!(names, ["octocat", "", "hubot"]);
+    }
+
+    #[test]
+    fn a_missing_file_is_an_error() {
+        assert!(read_username_lines("/nonexistent/usernames.txt").is_err());
+    }
+}
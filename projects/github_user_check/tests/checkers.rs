@@ -0,0 +1,288 @@
+use github_mock_server::{MockGitHub, MockResponse};
+use github_user_check::{
+    AsyncChecker, AsyncUserChecker, BlockingChecker, GitHubUser, GitHubUserSearch, Lookup,
+    RateLimiter, RetryPolicy, UserChecker,
+};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// retries without waiting, so the tests only wait where they mean to
+fn quick_retries(max_attempts: u32) -> RetryPolicy {
+    RetryPolicy {
+        max_attempts,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(1),
+        jitter: 0.0,
+    }
+}
+
+fn blocking_checker(server: &MockGitHub, max_attempts: u32) -> BlockingChecker {
+    BlockingChecker::new()
+        .with_lookup(Lookup::profile_page().with_base_url(&server.url()))
+        .with_retry_policy(quick_retries(max_attempts))
+}
+
+fn async_checker(server: &MockGitHub, max_attempts: u32) -> AsyncChecker {
+    AsyncChecker::new()
+        .with_lookup(Lookup::profile_page().with_base_url(&server.url()))
+        .with_retry_policy(quick_retries(max_attempts))
+}
+
+#[test]
+fn blocking_checker_tells_found_from_not_found() {
+    let server = MockGitHub::start();
+    server.script("/octocat", [MockResponse::found()]);
+    let checker = blocking_checker(&server, 3);
+
+    let found = checker.check("octocat");
+    assert_eq!(found.search, GitHubUserSearch::Found(None));
+    assert_eq!(found.attempts, 1);
+
+    // a 404 is final, it is never retried
+    let not_found = checker.check("ghost");
+    assert_eq!(not_found.search, GitHubUserSearch::NotFound);
+    assert_eq!(not_found.attempts, 1);
+    assert_eq!(server.request_count("/ghost"), 1);
+}
+
+#[tokio::test]
+async fn async_checker_tells_found_from_not_found() {
+    let server = MockGitHub::start();
+    server.script("/octocat", [MockResponse::found()]);
+    let checker = async_checker(&server, 3);
+
+    let found = checker.check("octocat").await;
+    assert_eq!(found.search, GitHubUserSearch::Found(None));
+
+    let not_found = checker.check("ghost").await;
+    assert_eq!(not_found.search, GitHubUserSearch::NotFound);
+    assert_eq!(server.request_count("/ghost"), 1);
+}
+
+#[test]
+fn blocking_checker_retries_rate_limits_and_server_errors() {
+    let server = MockGitHub::start();
+    server.script(
+        "/octocat",
+        [
+            MockResponse::rate_limited(0),
+            MockResponse::server_error(502),
+            MockResponse::found(),
+        ],
+    );
+    let checker = blocking_checker(&server, 3);
+
+    let result = checker.check("octocat");
+    assert_eq!(result.search, GitHubUserSearch::Found(None));
+    assert_eq!(result.attempts, 3);
+}
+
+#[tokio::test]
+async fn async_checker_retries_rate_limits_and_server_errors() {
+    let server = MockGitHub::start();
+    server.script(
+        "/octocat",
+        [
+            MockResponse::rate_limited(0),
+            MockResponse::server_error(502),
+            MockResponse::found(),
+        ],
+    );
+    let checker = async_checker(&server, 3);
+
+    let result = checker.check("octocat").await;
+    assert_eq!(result.search, GitHubUserSearch::Found(None));
+    assert_eq!(result.attempts, 3);
+}
+
+#[test]
+fn blocking_checker_reports_the_last_failure_once_the_attempts_run_out() {
+    let server = MockGitHub::start();
+    server.script("/octocat", [MockResponse::server_error(503)]);
+    server.script("/hubot", [MockResponse::rate_limited(60)]);
+    let checker = blocking_checker(&server, 2);
+
+    let result = checker.check("octocat");
+    assert_eq!(
+        result.search,
+        GitHubUserSearch::ServerError(reqwest::StatusCode::SERVICE_UNAVAILABLE)
+    );
+    assert_eq!(result.attempts, 2);
+    assert_eq!(server.request_count("/octocat"), 2);
+
+    let result = checker.check("hubot");
+    assert!(matches!(
+        result.search,
+        GitHubUserSearch::RateLimited { reset: Some(_) }
+    ));
+}
+
+#[tokio::test]
+async fn async_checker_reports_the_last_failure_once_the_attempts_run_out() {
+    let server = MockGitHub::start();
+    server.script("/octocat", [MockResponse::server_error(500)]);
+    let checker = async_checker(&server, 2);
+
+    let result = checker.check("octocat").await;
+    assert_eq!(
+        result.search,
+        GitHubUserSearch::ServerError(reqwest::StatusCode::INTERNAL_SERVER_ERROR)
+    );
+    assert_eq!(result.attempts, 2);
+}
+
+#[test]
+fn blocking_checker_times_out_on_slow_responses() {
+    let server = MockGitHub::start();
+    server.script(
+        "/octocat",
+        [MockResponse::found().with_delay(Duration::from_secs(2))],
+    );
+    let checker = blocking_checker(&server, 1)
+        .with_timeout(Duration::from_millis(200))
+        .unwrap();
+
+    assert_eq!(checker.check("octocat").search, GitHubUserSearch::Timeout);
+}
+
+#[tokio::test]
+async fn async_checker_times_out_on_slow_responses() {
+    let server = MockGitHub::start();
+    server.script(
+        "/octocat",
+        [MockResponse::found().with_delay(Duration::from_secs(2))],
+    );
+    let checker = async_checker(&server, 1)
+        .with_timeout(Duration::from_millis(200))
+        .unwrap();
+
+    assert_eq!(
+        checker.check("octocat").await.search,
+        GitHubUserSearch::Timeout
+    );
+}
+
+#[test]
+fn a_forbidden_response_is_only_a_rate_limit_when_no_requests_remain() {
+    let server = MockGitHub::start();
+    server.script(
+        "/octocat",
+        [MockResponse::new(403)
+            .with_header("x-ratelimit-remaining", "0")
+            .with_header("x-ratelimit-reset", "1700000000")],
+    );
+    server.script("/hubot", [MockResponse::new(403)]);
+    let checker = blocking_checker(&server, 1);
+
+    let reset = std::time::SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    assert_eq!(
+        checker.check("octocat").search,
+        GitHubUserSearch::RateLimited { reset: Some(reset) }
+    );
+    assert_eq!(
+        checker.check("hubot").search,
+        GitHubUserSearch::UnexpectedStatus(reqwest::StatusCode::FORBIDDEN)
+    );
+}
+
+#[test]
+fn blocking_checker_reads_the_api_record_and_sends_the_token() {
+    let server = MockGitHub::start();
+    server.script(
+        "/users/octocat",
+        [MockResponse::api_user("octocat", 583231)],
+    );
+    let checker = BlockingChecker::new().with_lookup(
+        Lookup::api(Some("secret"))
+            .unwrap()
+            .with_base_url(&server.url()),
+    );
+
+    let result = checker.check("octocat");
+    assert_eq!(
+        result.search,
+        GitHubUserSearch::Found(Some(GitHubUser {
+            login: "octocat".to_owned(),
+            id: 583231,
+            account_type: "User".to_owned(),
+            created_at: "2011-01-25T18:44:36Z".to_owned(),
+            public_repos: 8,
+        }))
+    );
+
+    let request = &server.requests()[0];
+    assert_eq!(request.headers["authorization"], "Bearer secret");
+    assert_eq!(request.headers["accept"], "application/vnd.github+json");
+}
+
+#[tokio::test]
+async fn async_checker_reads_the_api_record_without_a_token() {
+    let server = MockGitHub::start();
+    server.script(
+        "/users/octocat",
+        [MockResponse::api_user("octocat", 583231)],
+    );
+    server.script(
+        "/users/hubot",
+        [MockResponse::found().with_body("not json")],
+    );
+    let checker = AsyncChecker::new()
+        .with_lookup(Lookup::api(None).unwrap().with_base_url(&server.url()))
+        .with_retry_policy(quick_retries(1));
+
+    let result = checker.check("octocat").await;
+    assert!(matches!(
+        result.search,
+        GitHubUserSearch::Found(Some(GitHubUser { id: 583231, .. }))
+    ));
+    assert!(!server.requests()[0].headers.contains_key("authorization"));
+
+    // a body that is not a user record is reported, not taken as found
+    let result = checker.check("hubot").await;
+    assert!(matches!(
+        result.search,
+        GitHubUserSearch::InvalidResponse(_)
+    ));
+}
+
+#[test]
+fn blocking_checkers_sharing_a_limiter_pause_on_retry_after() {
+    let server = MockGitHub::start();
+    server.script(
+        "/octocat",
+        [MockResponse::rate_limited(1), MockResponse::found()],
+    );
+    server.script("/hubot", [MockResponse::found()]);
+    let limiter = Arc::new(RateLimiter::unlimited());
+    let checker = blocking_checker(&server, 2).with_rate_limiter(Arc::clone(&limiter));
+    let other_checker = blocking_checker(&server, 1).with_rate_limiter(limiter);
+
+    let start = Instant::now();
+    let result = checker.check("octocat");
+    assert_eq!(result.search, GitHubUserSearch::Found(None));
+    assert!(start.elapsed() >= Duration::from_secs(1));
+    // the pause is handed back to the caller instead of printed
+    assert!(result
+        .rate_limit_pause
+        .is_some_and(|pause| pause <= Duration::from_secs(1)));
+
+    // the pause is over, so the next check goes out at once
+    let start = Instant::now();
+    assert_eq!(other_checker.check("hubot").rate_limit_pause, None);
+    assert!(start.elapsed() < Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn async_checker_paces_requests_with_the_token_bucket() {
+    let server = MockGitHub::start();
+    let checker =
+        async_checker(&server, 1).with_rate_limiter(Arc::new(RateLimiter::new(Some(20.0), 1)));
+
+    // the first request takes the only token, the next four wait 50ms each
+    let start = Instant::now();
+    for username in ["a", "b", "c", "d", "e"] {
+        checker.check(username).await;
+    }
+    assert!(start.elapsed() >= Duration::from_millis(190));
+    assert_eq!(server.requests().len(), 5);
+}